@@ -1,19 +1,23 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     fs::{self as fs_sync, File},
     io::{ErrorKind, Read, Seek, SeekFrom},
     path::Path,
     str::FromStr,
+    sync::RwLock,
 };
 
 use lazy_static::lazy_static;
-use semver::{Comparator, Version};
+use semver::Version;
 use tokio::fs;
 
 use crate::{
+    config::Config,
     errors::CommandError,
+    logger::Logger,
+    node_modules::{NodeModules, NODE_MODULES_PATH},
     types::PackageLock,
-    versions::{Versions, EMPTY_VERSION, LATEST},
+    versions::{VersionSpec, Versions, LATEST},
 };
 
 pub struct CachedVersion {
@@ -23,19 +27,63 @@ pub struct CachedVersion {
 
 pub type CachedVersions = HashMap<String, CachedVersion>;
 
+// The folder inside of the cache directory (see Config::cache_directory()) that packages are cached in.
+pub const PACKAGE_CACHE_FOLDER: &str = "node-cache";
+
 lazy_static! {
     pub static ref CACHE_DIRECTORY: String = format!(
-        "{}/node-cache",
-        dirs::cache_dir()
-            .expect("Failed to find cache directory")
+        "{}/{PACKAGE_CACHE_FOLDER}",
+        Config::get()
+            .cache_directory()
             .to_str()
             .expect("Failed to convert cache directory to string")
     );
     pub static ref CACHED_VERSIONS: CachedVersions = Cache::get_cached_versions();
+    // Package locks that overrides changed for the project in the working directory, see Cache::read_lockfile().
+    static ref OVERRIDDEN_LOCKS: RwLock<BTreeMap<String, PackageLock>> = RwLock::new(
+        NodeModules::read_lock()
+            .map(|project_lock| project_lock.overridden_locks)
+            .unwrap_or_default()
+    );
 }
 
 pub struct Cache;
 impl Cache {
+    /// The directory a package is cached in, package string is formated as package@version.
+    /// The `/` of a scoped package is replaced with `+` so every package is a single directory in the cache (@types+node@20.0.0),
+    /// `+` can't be used in package names so this can't clash with an unscoped package.
+    /// Aliased packages are cached under the package they point to.
+    pub fn package_directory(package: &str) -> String {
+        let (package_name, version) =
+            Versions::parse_raw_package_details(Versions::real_package(package));
+        format!(
+            "{}/{}@{}",
+            *CACHE_DIRECTORY,
+            package_name.replace('/', "+"),
+            version
+        )
+    }
+
+    /// The lockfile of a cached package, package string is formated as package@version
+    /// Linked packages keep theirs next to the symlink, as the package folder is the user's own folder.
+    pub fn lockfile_path(package: &str) -> String {
+        let (_, version) = Versions::parse_raw_package_details(Versions::real_package(package));
+
+        match Versions::is_link_version(&version) {
+            true => format!("{}/click-lock.json", Self::package_directory(package)),
+            false => format!(
+                "{}/package/click-lock.json",
+                Self::package_directory(package)
+            ),
+        }
+    }
+
+    /// Turns the name of a directory in the cache back into package@version.
+    pub fn package_from_directory(directory_name: String) -> String {
+        let (package_name, version) = Versions::parse_raw_package_details(directory_name);
+        format!("{}@{}", package_name.replace('+', "/"), version)
+    }
+
     /// Returns a hashmap, each key is formatted as package@version
     /// and the value is a boolean of whether the package is the latest version or not.
     pub fn get_cached_versions() -> CachedVersions {
@@ -46,13 +94,19 @@ impl Cache {
 
         for entry in dir_contents {
             let entry = entry.expect("Failed to get directory entry");
-            let filename = entry.file_name().to_string_lossy().to_string();
+            let package =
+                Self::package_from_directory(entry.file_name().to_string_lossy().to_string());
+            let (name, version) = Versions::parse_raw_package_details(package.to_string());
 
-            let mut lock_file = File::open(format!(
-                "{}/{}/package/click-lock.json",
-                *CACHE_DIRECTORY, filename
-            ))
-            .expect("Failed to read package lock file");
+            // Packages installed from git or a local folder are never the latest version on the registry
+            if !Versions::is_registry_version(&version) {
+                continue;
+            }
+
+            // Packages without a lockfile failed to install so they can't be used
+            let Ok(mut lock_file) = File::open(Self::lockfile_path(&package)) else {
+                continue;
+            };
 
             // This is not an ideal method but it beats parsing the JSON of every installed package
             let start_byte = 12;
@@ -67,28 +121,30 @@ impl Cache {
             let is_latest_str = String::from_utf8(buf).unwrap();
             let is_latest = is_latest_str == "true";
 
-            let (name, version) = Versions::parse_raw_package_details(filename);
             cached_versions.insert(name, CachedVersion { version, is_latest });
         }
 
         cached_versions
     }
 
-    /// Checks if a package with a valid version matching with `semantic_version` is already in the cache
-    /// and returns `true` if so, `false` if otherwise, as well as the resolved version if it exists
+    /// Checks if a package with a valid version matching with `version_spec` is already in the cache
+    /// and returns `true` if so, `false` if otherwise, as well as the resolved version if it exists.
+    /// If more than one cached version matches, the newest one is used.
     pub async fn exists(
         package_name: &String,
         version: Option<&String>,
-        semantic_version: Option<&Comparator>,
+        version_spec: &VersionSpec,
     ) -> Result<(bool, Option<String>), CommandError> {
         if let Some(version) = version {
             if version == LATEST {
-                let latest_version = Self::get_latest_version_in_cache(package_name);
+                let latest_version = Self::get_latest_version_in_cache(package_name)
+                    .filter(|latest_version| version_spec.matches(latest_version));
                 return Ok((latest_version.is_some(), latest_version));
             }
 
+            // CACHED_VERSIONS only has one version of each package, so an exact version is looked up by its folder
             return Ok((
-                Self::is_in_cache(package_name, version),
+                Self::is_installed(&Versions::stringify(package_name, version)),
                 Some(version.to_string()),
             ));
         }
@@ -97,7 +153,11 @@ impl Cache {
             .await
             .map_err(CommandError::NoCacheDirectory)?;
 
-        let semantic_version = semantic_version.unwrap();
+        // Only the registry knows what a tag points to
+        let VersionSpec::Range(semantic_version) = version_spec else {
+            return Ok((false, None));
+        };
+        let mut newest_version: Option<(Version, String)> = None;
 
         while let Some(cache_entry) = cache_entries
             .next_entry()
@@ -106,27 +166,52 @@ impl Cache {
             .unwrap()
         {
             let filename = cache_entry.file_name().to_string_lossy().to_string();
-            if !filename.starts_with(package_name) {
+            let (entry_name, entry_version) =
+                Versions::parse_raw_package_details(Self::package_from_directory(filename));
+
+            // A prefix check isn't enough here, `react` would match `react-dom@1.0.0`
+            if &entry_name != package_name || !Versions::is_registry_version(&entry_version) {
+                continue;
+            }
+
+            // Packages without a lockfile are either being installed right now or failed to install
+            let entry_package = Versions::stringify(&entry_name, &entry_version);
+            if !Path::new(&Self::lockfile_path(&entry_package)).exists() {
                 continue;
             }
 
-            let (_, entry_version) = Versions::parse_raw_package_details(filename);
+            let version = match Version::from_str(entry_version.as_str()) {
+                Ok(version) => version,
+                Err(err) => {
+                    Logger::warn(format!(
+                        "Ignoring cached package '{package_name}@{entry_version}' as its version is not valid semver ({err})"
+                    ));
+                    continue;
+                }
+            };
+
+            if !semantic_version.matches(&version) {
+                continue;
+            }
 
-            let version = &Version::from_str(entry_version.as_str()).unwrap_or(EMPTY_VERSION);
-            if semantic_version.matches(version) {
-                return Ok((true, Some(entry_version)));
+            if newest_version.as_ref().is_none_or(|(newest_version, _)| {
+                Versions::compare(&version, newest_version).is_gt()
+            }) {
+                newest_version = Some((version, entry_version));
             }
         }
 
-        Ok((false, None))
+        match newest_version {
+            Some((_, entry_version)) => Ok((true, Some(entry_version))),
+            None => Ok((false, None)),
+        }
     }
 
-    pub fn is_in_cache(package: &String, version: &String) -> bool {
-        let cached_version = CACHED_VERSIONS.get(package);
-        match cached_version {
-            Some(ver) if &ver.version == version => true,
-            _ => false,
-        }
+    /// Checks if a package has been fully installed into the cache, package string is formated as package@version
+    /// Local packages never count as installed, the files in their folder can change between installs.
+    pub fn is_installed(package: &str) -> bool {
+        let (_, version) = Versions::parse_raw_package_details(Versions::real_package(package));
+        !Versions::is_local_version(&version) && Path::new(&Self::lockfile_path(package)).exists()
     }
 
     /// Checks if the latest version exists in the cache.
@@ -139,24 +224,93 @@ impl Cache {
         }
     }
 
+    /// Reads the package lock of a cached package.
     /// Package string is formated as package@version
+    /// If the project's overrides changed the dependencies of the package, the lock from the project lock is used instead.
+    pub fn read_lockfile(package: &str) -> Result<PackageLock, CommandError> {
+        let real_package = Versions::real_package(package);
+        if let Some(package_lock) = OVERRIDDEN_LOCKS.read().unwrap().get(&real_package) {
+            return Ok(package_lock.clone());
+        }
+
+        let lockfile_raw = fs_sync::read_to_string(Self::lockfile_path(package))
+            .map_err(CommandError::FailedToReadPackageLock)?;
+
+        serde_json::from_str::<PackageLock>(lockfile_raw.as_str())
+            .map_err(CommandError::FailedToParsePackageLock)
+    }
+
+    /// Sets the overridden lock of a package, or removes it if the package is no longer overridden.
+    pub fn set_overridden_lock(package: &str, package_lock: Option<PackageLock>) {
+        let mut overridden_locks = OVERRIDDEN_LOCKS.write().unwrap();
+
+        match package_lock {
+            Some(package_lock) => overridden_locks.insert(package.to_string(), package_lock),
+            None => overridden_locks.remove(package),
+        };
+    }
+
+    pub fn has_overridden_locks() -> bool {
+        !OVERRIDDEN_LOCKS.read().unwrap().is_empty()
+    }
+
+    pub fn overridden_locks() -> BTreeMap<String, PackageLock> {
+        OVERRIDDEN_LOCKS.read().unwrap().clone()
+    }
+
+    /// Returns the package and every package it depends on (directly or not) according to the package locks.
+    /// Package string is formated as package@version
+    pub fn dependency_tree(package: String) -> Result<HashSet<String>, CommandError> {
+        let mut packages = HashSet::new();
+        let mut pending = vec![package];
+
+        while let Some(package) = pending.pop() {
+            if packages.contains(&package) {
+                continue;
+            }
+
+            pending.extend(Self::read_lockfile(&package)?.dependencies);
+            packages.insert(package);
+        }
+
+        Ok(packages)
+    }
+
+    /// Links a cached package and all of its dependencies into the node modules folder.
+    /// Package string is formated as package@version, aliased packages are linked under their alias.
     pub fn load_cached_version(package: String) {
-        let lockfile_raw = fs_sync::read_to_string(format!(
-            "{}/{}/package/click-lock.json",
-            *CACHE_DIRECTORY, package
-        ))
-        .expect("Failed to read package lockfile");
+        // The package itself replaces whatever was linked under its name before, such as another version or an older copy of a tarball
+        let (package_name, _) = Versions::parse_raw_package_details(package.to_string());
+        let link_path = format!("{NODE_MODULES_PATH}/{package_name}");
+
+        if fs_sync::symlink_metadata(&link_path).is_ok() {
+            symlink::remove_symlink_dir(&link_path).expect("Failed to remove old package link");
+        }
 
-        let lockfile = serde_json::from_str::<PackageLock>(lockfile_raw.as_str()).unwrap();
-        let mut dependencies = lockfile.dependencies;
-        dependencies.push(package);
+        let mut linked_packages = HashSet::new();
+        let mut pending = vec![package];
 
-        for dependency in dependencies {
-            let (package_name, _) = Versions::parse_raw_package_details(dependency.to_string());
+        // Packages are linked before their dependencies, so if two versions share a name the one closest to the top wins.
+        while let Some(package) = pending.pop() {
+            if !linked_packages.insert(package.to_string()) {
+                continue;
+            }
+
+            let lockfile = Self::read_lockfile(&package).expect("Failed to read package lockfile");
+            pending.extend(lockfile.dependencies);
+
+            let (package_name, _) = Versions::parse_raw_package_details(package.to_string());
+            let link_path = format!("{NODE_MODULES_PATH}/{package_name}");
+
+            // Scoped packages are linked inside of node_modules/@scope
+            if let Some((scope, _)) = package_name.split_once('/') {
+                fs_sync::create_dir_all(format!("{NODE_MODULES_PATH}/{scope}"))
+                    .expect("Failed to create scope folder");
+            }
 
             let result = symlink::symlink_dir(
-                format!("{}/{}/package", *CACHE_DIRECTORY, dependency),
-                format!("./node_modules/{}", package_name),
+                format!("{}/package", Self::package_directory(&package)),
+                link_path,
             );
 
             match result {
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+
+use crate::{
+    errors::{CommandError, ParseError},
+    logger::Logger,
+    package_json::{DependencyType, PackageJsonEditor},
+    versions::{PackageDetails, VersionSpec, Versions},
+};
+
+use super::{
+    command_handler::{CommandArgs, CommandHandler},
+    install::{InstallHandler, InstallOptions},
+};
+
+/// Installs packages and saves them into the package.json.
+#[derive(Default)]
+pub struct AddHandler {
+    packages: Vec<PackageDetails>,
+    dependency_type: DependencyType,
+    save_exact: bool,
+    install_options: InstallOptions,
+}
+
+impl AddHandler {
+    pub fn new(packages: Vec<PackageDetails>, dependency_type: DependencyType) -> Self {
+        Self {
+            packages,
+            dependency_type,
+            save_exact: false,
+            install_options: InstallOptions::default(),
+        }
+    }
+
+    /// The version that gets written to the package.json.
+    /// The range the user asked for is kept, if they didn't ask for one then any compatible version of what was installed is allowed.
+    /// Aliases keep pointing at the same package (npm:lodash@^4.17.21), git, local and remote packages at the same repository, folder or url.
+    fn saved_version(&self, version_spec: &VersionSpec, installed_version: &str) -> String {
+        match (version_spec, self.save_exact) {
+            (VersionSpec::Git(_) | VersionSpec::Local(_) | VersionSpec::Remote(_), _) => {
+                version_spec.to_string()
+            }
+            (VersionSpec::Alias(_, real_version_spec), _) => format!(
+                "{}{}",
+                version_spec.alias_prefix(),
+                self.saved_version(real_version_spec, installed_version)
+            ),
+            (_, true) => installed_version.to_string(),
+            (VersionSpec::Range(semantic_version), false) => semantic_version.to_string(),
+            (VersionSpec::Tag(_), false) => format!("^{installed_version}"),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for AddHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        for arg in args {
+            match arg.as_str() {
+                "--save-dev" | "-D" => self.dependency_type = DependencyType::Dev,
+                "--save-optional" | "-O" => self.dependency_type = DependencyType::Optional,
+                "--save-peer" => self.dependency_type = DependencyType::Peer,
+                "--save-exact" | "-E" => self.save_exact = true,
+                "--strict-peer-dependencies" => {
+                    self.install_options.strict_peer_dependencies = true
+                }
+                _ if arg.starts_with('-') => return Err(ParseError::UnknownFlag(arg)),
+                _ => self
+                    .packages
+                    .push(Versions::parse_semantic_package_details(arg)?),
+            }
+        }
+
+        if self.packages.is_empty() {
+            return Err(ParseError::MissingArgument(String::from("package name")));
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let results =
+            InstallHandler::install_packages(&self.packages, &self.install_options).await?;
+        let mut package_json = PackageJsonEditor::open()?;
+        let mut failed_count = 0;
+
+        for (package_details, result) in self.packages.iter().zip(results) {
+            // Failed packages are already reported by the installer
+            let Ok(stringified) = result else {
+                failed_count += 1;
+                continue;
+            };
+
+            let (package_name, _) = Versions::parse_raw_package_details(stringified.to_string());
+            let (_, installed_version) =
+                Versions::parse_raw_package_details(Versions::real_package(&stringified));
+            let saved_version = self.saved_version(&package_details.1, &installed_version);
+
+            package_json.set_dependency(self.dependency_type, &package_name, &saved_version);
+            Logger::info(format!(
+                "Saved '{package_name}@{saved_version}' to package.json"
+            ));
+        }
+
+        package_json.save()?;
+
+        match failed_count {
+            0 => Ok(()),
+            _ => Err(CommandError::PackagesFailed(failed_count)),
+        }
+    }
+}
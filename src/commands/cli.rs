@@ -0,0 +1,333 @@
+use std::{env, path::Path};
+
+use crate::{
+    config::Config,
+    errors::ParseError,
+    logger::{LogLevel, Logger},
+};
+
+use super::command_handler::{Command, Flag, COMMANDS};
+
+pub const GLOBAL_FLAGS: &[Flag] = &[
+    Flag {
+        names: &["--cwd"],
+        value: Some("<dir>"),
+        description: "Run as if click was started in <dir>",
+    },
+    Flag {
+        names: &["--registry"],
+        value: Some("<url>"),
+        description: "The registry packages are fetched from",
+    },
+    Flag {
+        names: &["--cache"],
+        value: Some("<dir>"),
+        description: "The folder packages are cached in",
+    },
+    Flag {
+        names: &["--loglevel"],
+        value: Some("<level>"),
+        description: "One of silent, error, warn, info or verbose",
+    },
+    Flag {
+        names: &["--json"],
+        value: None,
+        description: "Print the output as json for commands that support it",
+    },
+    Flag {
+        names: &["-h", "--help"],
+        value: None,
+        description: "Print help",
+    },
+    Flag {
+        names: &["-v", "--version"],
+        value: None,
+        description: "Print the version of click",
+    },
+];
+
+// Suggestions are only made if the typo is this close to the real name
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+pub enum Invocation {
+    Help(Option<&'static Command>),
+    Version,
+    Run(&'static Command, Vec<String>),
+}
+
+pub struct Cli;
+impl Cli {
+    /// Takes the global flags out of the arguments and applies them, they can be placed anywhere in the arguments.
+    /// The remaining arguments are checked against the flags of the command they are for.
+    pub fn parse(args: Vec<String>) -> Result<Invocation, ParseError> {
+        let mut config = Config::default();
+        let mut command: Option<&'static Command> = None;
+        let mut command_args = Vec::new();
+        let mut is_help = false;
+        let mut is_version = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag_name, inline_value) = match arg.split_once('=') {
+                Some((flag_name, value)) if arg.starts_with("--") => {
+                    (flag_name.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.to_string(), None),
+            };
+
+            match flag_name.as_str() {
+                "--" => {
+                    command_args.extend(args.by_ref());
+                }
+                "--cwd" | "--registry" | "--cache" | "--loglevel" => {
+                    let value = match inline_value {
+                        Some(value) => value,
+                        None => args
+                            .next()
+                            .ok_or(ParseError::MissingArgument(flag_name.to_string()))?,
+                    };
+
+                    Self::apply_global_flag(&mut config, &flag_name, value)?;
+                }
+                "--json" => config.json = true,
+                "-h" | "--help" => is_help = true,
+                "-v" | "--version" => is_version = true,
+                "help" if command.is_none() && !is_help => is_help = true,
+                _ if command.is_none() && arg.starts_with('-') => {
+                    return Err(ParseError::UnknownCommandFlag(
+                        arg,
+                        String::from("click"),
+                        Self::suggest(&flag_name, Self::flag_names(GLOBAL_FLAGS)),
+                    ));
+                }
+                _ if command.is_none() => command = Some(Self::find_command(&arg)?),
+                _ => command_args.push(arg),
+            }
+        }
+
+        Config::set(config);
+
+        if is_version && command.is_none() {
+            return Ok(Invocation::Version);
+        }
+
+        let Some(command) = command else {
+            return Ok(Invocation::Help(None));
+        };
+
+        if is_help {
+            return Ok(Invocation::Help(Some(command)));
+        }
+
+        let command_args = Self::check_flags(command, command_args)?;
+        Ok(Invocation::Run(command, command_args))
+    }
+
+    fn apply_global_flag(
+        config: &mut Config,
+        flag_name: &str,
+        value: String,
+    ) -> Result<(), ParseError> {
+        match flag_name {
+            "--cwd" => env::set_current_dir(Path::new(&value))
+                .map_err(|_| ParseError::InvalidArgument(flag_name.to_string(), value))?,
+            "--registry" => config.registry = value.trim_end_matches('/').to_string(),
+            // Relative to where click was started (or --cwd if it came first), as the working directory can still change
+            "--cache" => {
+                config.cache = Some(
+                    env::current_dir()
+                        .map_err(|_| {
+                            ParseError::InvalidArgument(flag_name.to_string(), value.to_string())
+                        })?
+                        .join(&value),
+                )
+            }
+            "--loglevel" => config.log_level = value.parse::<LogLevel>()?,
+            _ => unreachable!("Not a global flag with a value"),
+        }
+
+        Ok(())
+    }
+
+    /// Finds a command by its name or one of its aliases.
+    pub fn find_command(name: &str) -> Result<&'static Command, ParseError> {
+        let name = name.to_lowercase();
+
+        COMMANDS
+            .iter()
+            .find(|command| command.name == name || command.aliases.contains(&name.as_str()))
+            .ok_or_else(|| {
+                let command_names = COMMANDS.iter().flat_map(|command| {
+                    [command.name]
+                        .into_iter()
+                        .chain(command.aliases.iter().copied())
+                });
+
+                ParseError::CommandNotFound(name.to_string(), Self::suggest(&name, command_names))
+            })
+    }
+
+    /// Makes sure every flag is one the command accepts.
+    /// Flags with a value given as `--flag=value` are split into two arguments so commands only need to handle one form.
+    fn check_flags(command: &Command, args: Vec<String>) -> Result<Vec<String>, ParseError> {
+        let mut checked_args = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                checked_args.push(arg);
+                continue;
+            }
+
+            let (flag_name, inline_value) = match arg.split_once('=') {
+                Some((flag_name, value)) => (flag_name.to_string(), Some(value.to_string())),
+                None => (arg.to_string(), None),
+            };
+
+            let flag = command
+                .flags
+                .iter()
+                .find(|flag| flag.names.contains(&flag_name.as_str()))
+                .ok_or_else(|| {
+                    ParseError::UnknownCommandFlag(
+                        arg.to_string(),
+                        format!("click {}", command.name),
+                        Self::suggest(&flag_name, Self::flag_names(command.flags)),
+                    )
+                })?;
+
+            match (flag.value, inline_value) {
+                (Some(_), Some(value)) => checked_args.extend([flag_name, value]),
+                (Some(_), None) => {
+                    checked_args.push(flag_name);
+                    checked_args.extend(args.next());
+                }
+                (None, Some(_)) => return Err(ParseError::UnexpectedFlagValue(flag_name)),
+                (None, None) => checked_args.push(flag_name),
+            }
+        }
+
+        Ok(checked_args)
+    }
+
+    fn flag_names(flags: &'static [Flag]) -> impl Iterator<Item = &'static str> {
+        flags.iter().flat_map(|flag| flag.names.iter().copied())
+    }
+
+    /// Returns the closest candidate to what was typed, if any are close enough.
+    fn suggest<'a>(typed: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+        candidates
+            .map(|candidate| (Self::edit_distance(typed, candidate), candidate))
+            .filter(|(distance, candidate)| {
+                *distance <= MAX_SUGGESTION_DISTANCE && *distance < candidate.len()
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate.to_string())
+    }
+
+    /// Levenshtein distance between two strings.
+    fn edit_distance(a: &str, b: &str) -> usize {
+        let b = b.chars().collect::<Vec<_>>();
+        let mut previous_row = (0..=b.len()).collect::<Vec<_>>();
+
+        for (i, a_char) in a.chars().enumerate() {
+            let mut current_row = vec![i + 1];
+
+            for (j, b_char) in b.iter().enumerate() {
+                let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+                current_row.push(
+                    (previous_row[j] + substitution_cost)
+                        .min(previous_row[j + 1] + 1)
+                        .min(current_row[j] + 1),
+                );
+            }
+
+            previous_row = current_row;
+        }
+
+        previous_row[b.len()]
+    }
+
+    pub fn print_help() {
+        println!("Usage: click <command> [options]");
+        println!();
+        println!("A fast package manager for NodeJS written in Rust.");
+        println!();
+        println!("Commands:");
+
+        let rows = COMMANDS
+            .iter()
+            .map(|command| {
+                let names = [command.name]
+                    .into_iter()
+                    .chain(command.aliases.iter().copied())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                (names, command.description)
+            })
+            .collect::<Vec<_>>();
+
+        Self::print_rows(&rows);
+        println!();
+        Self::print_flags("Global options", GLOBAL_FLAGS);
+        println!();
+        println!("Run 'click help <command>' for more information on a command.");
+    }
+
+    pub fn print_command_help(command: &Command) {
+        println!("Usage: click {} {}", command.name, command.usage);
+        println!();
+        println!("{}", command.description);
+
+        if !command.aliases.is_empty() {
+            println!();
+            println!("Aliases: {}", command.aliases.join(", "));
+        }
+
+        if !command.flags.is_empty() {
+            println!();
+            Self::print_flags("Options", command.flags);
+        }
+
+        println!();
+        Self::print_flags("Global options", GLOBAL_FLAGS);
+    }
+
+    pub fn print_version() {
+        println!("click {}", env!("CARGO_PKG_VERSION"));
+    }
+
+    fn print_flags(title: &str, flags: &[Flag]) {
+        println!("{title}:");
+
+        let rows = flags
+            .iter()
+            .map(|flag| {
+                let names = flag.names.join(", ");
+                let usage = match flag.value {
+                    Some(value) => format!("{names} {value}"),
+                    None => names,
+                };
+
+                (usage, flag.description)
+            })
+            .collect::<Vec<_>>();
+
+        Self::print_rows(&rows);
+    }
+
+    fn print_rows(rows: &[(String, &str)]) {
+        let width = rows.iter().map(|(usage, _)| usage.len()).max().unwrap_or(0);
+
+        for (usage, description) in rows {
+            println!("  {usage:width$}  {description}");
+        }
+    }
+
+    /// Reports an error which stopped a command from running, the process exits with a failing status.
+    pub fn exit_with_error(message: impl std::fmt::Display) -> ! {
+        Logger::error(message);
+        std::process::exit(1);
+    }
+}
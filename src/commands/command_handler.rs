@@ -1,41 +1,235 @@
 use async_trait::async_trait;
 use std::env::Args;
 
-use crate::errors::{
-    CommandError,
-    ParseError::{self, CommandNotFound},
+use crate::errors::{CommandError, ParseError};
+
+use super::{
+    add::AddHandler,
+    cli::{Cli, Invocation},
+    init::InitHandler,
+    install::InstallHandler,
+    list::ListHandler,
+    outdated::OutdatedHandler,
+    prune::PruneHandler,
+    remove::RemoveHandler,
+    update::UpdateHandler,
+    why::WhyHandler,
 };
 
-use super::install::InstallHandler;
+pub type CommandArgs = std::vec::IntoIter<String>;
 
 #[async_trait]
 pub trait CommandHandler {
-    fn parse(&mut self, args: &mut Args) -> Result<(), ParseError>;
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError>;
     async fn execute(&self) -> Result<(), CommandError>;
 }
 
+pub struct Flag {
+    pub names: &'static [&'static str],
+    pub value: Option<&'static str>, // The placeholder shown in the help menu if the flag takes a value
+    pub description: &'static str,
+}
+
+/// Describes a command for the help menu and argument checking, `handler` creates the handler which runs it.
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub flags: &'static [Flag],
+    pub handler: fn() -> Box<dyn CommandHandler>,
+}
+
+// Shared by every command that installs packages.
+const STRICT_PEER_DEPENDENCIES_FLAG: Flag = Flag {
+    names: &["--strict-peer-dependencies"],
+    value: None,
+    description: "Fail instead of warning when a peer dependency conflicts with what is installed",
+};
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "init",
+        aliases: &["create"],
+        usage: "[options]",
+        description: "Create a package.json by answering a few questions, or update the existing one",
+        flags: &[
+            Flag {
+                names: &["-y", "--yes"],
+                value: None,
+                description: "Skip the questions and use the defaults",
+            },
+            Flag {
+                names: &["--scope"],
+                value: Some("<scope>"),
+                description: "Put the package name in a scope, such as @scope/name",
+            },
+            Flag {
+                names: &["--template"],
+                value: Some("<template>"),
+                description: "Start from a template and install its dependencies, one of express, typescript or jest",
+            },
+        ],
+        handler: || Box::<InitHandler>::default(),
+    },
+    Command {
+        name: "install",
+        aliases: &["i"],
+        usage: "[<package>[@<version>] ...]",
+        description: "Install packages, or every dependency in the package.json if none are given",
+        flags: &[
+            STRICT_PEER_DEPENDENCIES_FLAG,
+            Flag {
+                names: &["--omit"],
+                value: Some("<type>"),
+                description: "Leave out a type of dependency along with anything only it needs, only dev is supported",
+            },
+            Flag {
+                names: &["--production"],
+                value: None,
+                description: "Same as --omit dev",
+            },
+        ],
+        handler: || Box::<InstallHandler>::default(),
+    },
+    Command {
+        name: "add",
+        aliases: &[],
+        usage: "<package>[@<version>] ... [options]",
+        description: "Install packages and save them to the package.json",
+        flags: &[
+            Flag {
+                names: &["-D", "--save-dev"],
+                value: None,
+                description: "Save to devDependencies",
+            },
+            Flag {
+                names: &["-O", "--save-optional"],
+                value: None,
+                description: "Save to optionalDependencies",
+            },
+            Flag {
+                names: &["--save-peer"],
+                value: None,
+                description: "Save to peerDependencies",
+            },
+            Flag {
+                names: &["-E", "--save-exact"],
+                value: None,
+                description: "Save the exact version that was installed instead of a range",
+            },
+            STRICT_PEER_DEPENDENCIES_FLAG,
+        ],
+        handler: || Box::<AddHandler>::default(),
+    },
+    Command {
+        name: "uninstall",
+        aliases: &["remove", "rm", "un"],
+        usage: "<package> ...",
+        description: "Remove packages along with any dependencies that are no longer needed",
+        flags: &[],
+        handler: || Box::<RemoveHandler>::default(),
+    },
+    Command {
+        name: "prune",
+        aliases: &[],
+        usage: "[options]",
+        description: "Remove linked packages that nothing in the package.json needs",
+        flags: &[Flag {
+            names: &["--production"],
+            value: None,
+            description: "Also remove packages that only devDependencies need",
+        }],
+        handler: || Box::<PruneHandler>::default(),
+    },
+    Command {
+        name: "update",
+        aliases: &["up"],
+        usage: "[<package> ...] [options]",
+        description: "Move dependencies to the newest version their range allows",
+        flags: &[
+            Flag {
+                names: &["--latest"],
+                value: None,
+                description: "Move to the latest version even if it's outside of the range, and save it to the package.json",
+            },
+            STRICT_PEER_DEPENDENCIES_FLAG,
+        ],
+        handler: || Box::<UpdateHandler>::default(),
+    },
+    Command {
+        name: "outdated",
+        aliases: &[],
+        usage: "",
+        description: "List dependencies that are behind the latest version, exits with a status of 1 if there are any",
+        flags: &[],
+        handler: || Box::<OutdatedHandler>::default(),
+    },
+    Command {
+        name: "list",
+        aliases: &["ls"],
+        usage: "[<package>] [options]",
+        description: "Print the installed dependency tree, or only the parts that lead to a package",
+        flags: &[
+            Flag {
+                names: &["--depth"],
+                value: Some("<n>"),
+                description: "Only print dependencies this deep",
+            },
+            Flag {
+                names: &["--prod", "--production"],
+                value: None,
+                description: "Only print dependencies and optionalDependencies",
+            },
+            Flag {
+                names: &["--dev"],
+                value: None,
+                description: "Only print devDependencies",
+            },
+            Flag {
+                names: &["--parseable"],
+                value: None,
+                description: "Print every package once per line",
+            },
+        ],
+        handler: || Box::<ListHandler>::default(),
+    },
+    Command {
+        name: "why",
+        aliases: &["explain"],
+        usage: "<package>[@<version>]",
+        description: "Print every dependency path that leads to a package",
+        flags: &[],
+        handler: || Box::<WhyHandler>::default(),
+    },
+];
+
 pub async fn handle_args(mut args: Args) -> Result<(), ParseError> {
     args.next(); // Remove initial binary argument
 
-    let command = match args.next() {
-        Some(command) => command,
-        None => {
-            // TODO(conaticus): Implement help menu
-            println!("No help menu implemented yet.");
+    let (command, command_args) = match Cli::parse(args.collect())? {
+        Invocation::Help(None) => {
+            Cli::print_help();
+            return Ok(());
+        }
+        Invocation::Help(Some(command)) => {
+            Cli::print_command_help(command);
             return Ok(());
         }
+        Invocation::Version => {
+            Cli::print_version();
+            return Ok(());
+        }
+        Invocation::Run(command, command_args) => (command, command_args),
     };
 
-    let mut command_handler: Box<dyn CommandHandler> = match command.to_lowercase().as_str() {
-        "install" => Box::<InstallHandler>::default(),
-        _ => return Err(CommandNotFound(command.to_string())),
-    };
+    let mut command_handler = (command.handler)();
 
-    command_handler.parse(&mut args)?;
+    command_handler.parse(&mut command_args.into_iter())?;
     let command_result = command_handler.execute().await;
 
     if let Err(e) = command_result {
-        println!("Command error: {e}");
+        Cli::exit_with_error(format!("Command error: {e}"));
     }
 
     Ok(())
@@ -0,0 +1,420 @@
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    process,
+};
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    errors::{CommandError, ParseError},
+    logger::Logger,
+    package_json::{DependencyType, PackageJsonEditor},
+    versions::VersionSpec,
+};
+
+use super::{
+    add::AddHandler,
+    command_handler::{CommandArgs, CommandHandler},
+};
+
+const DEFAULT_VERSION: &str = "1.0.0";
+const DEFAULT_MAIN: &str = "index.js";
+const DEFAULT_LICENSE: &str = "ISC";
+const DEFAULT_TEST_SCRIPT: &str = "echo \"Error: no test specified\" && exit 1";
+
+const MAX_NAME_LENGTH: usize = 214;
+
+/// A starting point for a new package, its dependencies are installed the same way `click add` would install them.
+pub struct Template {
+    pub name: &'static str,
+    pub main: Option<&'static str>,
+    pub scripts: &'static [(&'static str, &'static str)],
+    pub dependencies: &'static [&'static str],
+    pub dev_dependencies: &'static [&'static str],
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "express",
+        main: None,
+        scripts: &[("start", "node index.js")],
+        dependencies: &["express"],
+        dev_dependencies: &["nodemon"],
+    },
+    Template {
+        name: "typescript",
+        main: Some("dist/index.js"),
+        scripts: &[("build", "tsc"), ("start", "node dist/index.js")],
+        dependencies: &[],
+        dev_dependencies: &["typescript"],
+    },
+    Template {
+        name: "jest",
+        main: None,
+        scripts: &[("test", "jest")],
+        dependencies: &[],
+        dev_dependencies: &["jest"],
+    },
+];
+
+/// Creates a package.json in the working directory by asking for each field, or updates the one that is already there.
+#[derive(Default)]
+pub struct InitHandler {
+    yes: bool,
+    scope: Option<String>,
+    template: Option<&'static Template>,
+}
+
+impl InitHandler {
+    /// Asks a question on the terminal, the default is used if nothing is entered (or `--yes` was given).
+    fn ask(&self, question: &str, default: &str) -> Result<String, CommandError> {
+        if self.yes {
+            return Ok(default.to_string());
+        }
+
+        match default.is_empty() {
+            true => print!("{question} "),
+            false => print!("{question} ({default}) "),
+        }
+
+        io::stdout()
+            .flush()
+            .map_err(CommandError::FailedToWriteFile)?;
+
+        let mut answer = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut answer)
+            .map_err(CommandError::FailedToReadInput)?;
+
+        match answer.trim() {
+            "" => Ok(default.to_string()),
+            answer => Ok(answer.to_string()),
+        }
+    }
+
+    /// Keeps asking for a name until a valid one is given.
+    fn ask_name(&self, default: &str) -> Result<String, CommandError> {
+        loop {
+            let name = self.ask("package name:", default)?;
+
+            match Self::validate_name(&name) {
+                Ok(()) => return Ok(name),
+                Err(reason) if self.yes => {
+                    return Err(CommandError::InvalidPackageName(name, reason))
+                }
+                Err(reason) => Logger::error(format!("Sorry, {reason}")),
+            }
+        }
+    }
+
+    /// The same rules npm uses for the names of new packages.
+    fn validate_name(name: &str) -> Result<(), String> {
+        let unscoped_name = match name.strip_prefix('@') {
+            Some(scoped_name) => match scoped_name.split_once('/') {
+                Some((scope, unscoped_name)) if Self::is_url_safe(scope) => unscoped_name,
+                _ => return Err(String::from("the scope must look like '@scope/name'")),
+            },
+            None => name,
+        };
+
+        if unscoped_name.is_empty() {
+            return Err(String::from("name cannot be empty"));
+        }
+
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(format!(
+                "name cannot be longer than {MAX_NAME_LENGTH} characters"
+            ));
+        }
+
+        if unscoped_name.starts_with('.') || unscoped_name.starts_with('_') {
+            return Err(String::from(
+                "name cannot start with a period or underscore",
+            ));
+        }
+
+        if name.to_lowercase() != name {
+            return Err(String::from("name can no longer contain capital letters"));
+        }
+
+        if !Self::is_url_safe(unscoped_name) {
+            return Err(String::from(
+                "name can only contain URL-friendly characters",
+            ));
+        }
+
+        if unscoped_name == "node_modules" || unscoped_name == "favicon.ico" {
+            return Err(format!("{unscoped_name} is not a valid package name"));
+        }
+
+        Ok(())
+    }
+
+    fn is_url_safe(name: &str) -> bool {
+        !name.is_empty()
+            && name.chars().all(|char| {
+                char.is_ascii_lowercase()
+                    || char.is_ascii_digit()
+                    || matches!(char, '-' | '.' | '_' | '~')
+            })
+    }
+
+    /// Turns the name of the working directory into a valid package name, with the scope in front of it if one was given.
+    fn default_name(&self) -> Result<String, CommandError> {
+        let working_directory = env::current_dir().map_err(CommandError::NoWorkingDirectory)?;
+        let directory_name = working_directory
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let name = directory_name
+            .chars()
+            .map(|char| match Self::is_url_safe(&char.to_string()) {
+                true => char,
+                false => '-',
+            })
+            .collect::<String>();
+
+        let name = name.trim_start_matches(['.', '_', '-']);
+        let name = match name.is_empty() {
+            true => "package",
+            false => name,
+        };
+
+        match &self.scope {
+            Some(scope) => Ok(format!("@{scope}/{name}")),
+            None => Ok(name.to_string()),
+        }
+    }
+
+    /// Reads a value from the git config, None if git isn't installed or the value isn't set.
+    fn git_config(key: &str) -> Option<String> {
+        let output = process::Command::new("git")
+            .args(["config", "--get", key])
+            .output()
+            .ok()?;
+
+        let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+
+        match output.status.success() && !value.is_empty() {
+            true => Some(value),
+            false => None,
+        }
+    }
+
+    fn default_author() -> String {
+        match (
+            Self::git_config("user.name"),
+            Self::git_config("user.email"),
+        ) {
+            (Some(name), Some(email)) => format!("{name} <{email}>"),
+            (Some(name), None) => name,
+            _ => String::new(),
+        }
+    }
+
+    /// Uses the value already in the package.json as the default if there is one.
+    fn existing_or(package_json: &PackageJsonEditor, field_name: &str, default: String) -> String {
+        match package_json.field(field_name) {
+            Some(Value::String(value)) => value.to_string(),
+            _ => default,
+        }
+    }
+
+    /// Sets a string field, empty answers are left out of the package.json like npm does.
+    fn set_answer(package_json: &mut PackageJsonEditor, field_name: &str, answer: String) {
+        if !answer.is_empty() {
+            package_json.set_field(field_name, Value::String(answer));
+        }
+    }
+
+    fn ask_fields(&self, package_json: &mut PackageJsonEditor) -> Result<(), CommandError> {
+        let name = Self::existing_or(package_json, "name", self.default_name()?);
+        let name = self.ask_name(&name)?;
+        package_json.set_field("name", Value::String(name));
+
+        let version = Self::existing_or(package_json, "version", DEFAULT_VERSION.to_string());
+        let version = self.ask("version:", &version)?;
+        package_json.set_field("version", Value::String(version));
+
+        let description = Self::existing_or(package_json, "description", String::new());
+        let description = self.ask("description:", &description)?;
+        package_json.set_field("description", Value::String(description));
+
+        let main = self
+            .template
+            .and_then(|template| template.main)
+            .unwrap_or(DEFAULT_MAIN);
+        let main = Self::existing_or(package_json, "main", main.to_string());
+        let main = self.ask("entry point:", &main)?;
+        Self::set_answer(package_json, "main", main);
+
+        self.ask_scripts(package_json)?;
+
+        let repository = match package_json.field("repository") {
+            Some(Value::Object(repository)) => repository
+                .get("url")
+                .and_then(|url| url.as_str())
+                .map(|url| url.to_string()),
+            Some(Value::String(repository)) => Some(repository.to_string()),
+            _ => Self::git_config("remote.origin.url"),
+        };
+        let repository = self.ask("git repository:", &repository.unwrap_or_default())?;
+
+        if !repository.is_empty() {
+            package_json.set_field("repository", json!({ "type": "git", "url": repository }));
+        }
+
+        let author = Self::existing_or(package_json, "author", Self::default_author());
+        let author = self.ask("author:", &author)?;
+        package_json.set_field("author", Value::String(author));
+
+        let license = Self::existing_or(package_json, "license", DEFAULT_LICENSE.to_string());
+        let license = self.ask("license:", &license)?;
+        Self::set_answer(package_json, "license", license);
+
+        Ok(())
+    }
+
+    /// Asks for the test command and adds the scripts of the template, scripts that are already there are kept.
+    fn ask_scripts(&self, package_json: &mut PackageJsonEditor) -> Result<(), CommandError> {
+        let mut scripts = match package_json.field("scripts") {
+            Some(Value::Object(scripts)) => scripts.clone(),
+            _ => Map::new(),
+        };
+
+        let template_scripts = self
+            .template
+            .map(|template| template.scripts)
+            .unwrap_or_default();
+
+        for (script_name, script) in template_scripts {
+            if !scripts.contains_key(*script_name) {
+                scripts.insert(script_name.to_string(), Value::String(script.to_string()));
+            }
+        }
+
+        let test_script = match scripts.get("test") {
+            Some(Value::String(test_script)) => test_script.to_string(),
+            _ => DEFAULT_TEST_SCRIPT.to_string(),
+        };
+
+        let test_script = self.ask("test command:", &test_script)?;
+        scripts.insert(String::from("test"), Value::String(test_script));
+
+        package_json.set_field("scripts", Value::Object(scripts));
+        Ok(())
+    }
+
+    /// Shows the package.json that is about to be written and asks if it's ok.
+    fn confirm(&self, package_json: &PackageJsonEditor) -> Result<bool, CommandError> {
+        if self.yes {
+            return Ok(true);
+        }
+
+        let working_directory = env::current_dir().map_err(CommandError::NoWorkingDirectory)?;
+
+        println!();
+        println!(
+            "About to write to {}:",
+            working_directory.join("package.json").display()
+        );
+        println!();
+        println!("{}", package_json.to_pretty_string()?);
+
+        let answer = self.ask("Is this OK?", "yes")?;
+        Ok(answer.to_lowercase().starts_with('y'))
+    }
+
+    /// Installs the dependencies of the template and saves them to the package.json.
+    /// Returns the number of packages that failed to install.
+    async fn install_template(template: &Template) -> Result<usize, CommandError> {
+        let mut failed_count = 0;
+
+        for (dependency_type, package_names) in [
+            (DependencyType::Regular, template.dependencies),
+            (DependencyType::Dev, template.dev_dependencies),
+        ] {
+            if package_names.is_empty() {
+                continue;
+            }
+
+            let packages = package_names
+                .iter()
+                .map(|package_name| (package_name.to_string(), VersionSpec::default()))
+                .collect();
+
+            match AddHandler::new(packages, dependency_type).execute().await {
+                Ok(()) => (),
+                Err(CommandError::PackagesFailed(count)) => failed_count += count,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(failed_count)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for InitHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--yes" | "-y" => self.yes = true,
+                "--scope" => {
+                    let scope = args
+                        .next()
+                        .ok_or(ParseError::MissingArgument(String::from("scope")))?;
+
+                    let trimmed_scope = scope.trim_start_matches('@').trim_end_matches('/');
+                    if !Self::is_url_safe(trimmed_scope) {
+                        return Err(ParseError::InvalidArgument(arg, scope));
+                    }
+
+                    self.scope = Some(trimmed_scope.to_string());
+                }
+                "--template" => {
+                    let template_name = args
+                        .next()
+                        .ok_or(ParseError::MissingArgument(String::from("template")))?;
+
+                    self.template = Some(
+                        TEMPLATES
+                            .iter()
+                            .find(|template| template.name == template_name)
+                            .ok_or(ParseError::InvalidArgument(arg, template_name))?,
+                    );
+                }
+                _ => return Err(ParseError::UnknownFlag(arg)),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let mut package_json = PackageJsonEditor::open()?;
+
+        self.ask_fields(&mut package_json)?;
+
+        if !self.confirm(&package_json)? {
+            Logger::info("Aborted");
+            return Ok(());
+        }
+
+        package_json.save()?;
+        Logger::info("Wrote to package.json");
+
+        let Some(template) = self.template else {
+            return Ok(());
+        };
+
+        match Self::install_template(template).await? {
+            0 => Ok(()),
+            failed_count => Err(CommandError::PackagesFailed(failed_count)),
+        }
+    }
+}
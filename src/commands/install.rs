@@ -1,40 +1,89 @@
 use std::{
-    collections::HashMap,
-    env::Args,
-    fs::File,
+    collections::{HashMap, HashSet},
+    fs::{self, File},
     io::Write,
+    path::{Path, PathBuf},
     sync::{mpsc::channel, Arc, Mutex},
 };
 
 use async_trait::async_trait;
-use semver::Comparator;
 
 use crate::{
-    cache::{Cache, CACHE_DIRECTORY},
+    cache::Cache,
+    config::Config,
     errors::{CommandError, ParseError},
-    installer::{DependencyMapMutex, InstallContext, Installer, PackageBytes, PackageInfo},
+    installer::{
+        DependencyMapMutex, FailureMapMutex, InstallContext, Installer, LinkedDirectoriesMutex,
+        PackageBytes, PackageInfo,
+    },
+    local::Local,
+    logger::Logger,
+    node_modules::NodeModules,
+    overrides::Overrides,
+    package_json::{DependencyType, PackageJson},
+    peers::{MissingPeer, Peers},
+    platform::Platform,
+    remote::Remote,
+    types::{DependencyMap, VersionData},
     util::{self, TaskAllocator},
-    versions::Versions,
+    versions::{PackageDetails, VersionSpec, Versions},
 };
 
-use super::command_handler::CommandHandler;
+use super::command_handler::{CommandArgs, CommandHandler};
+
+/// Options that change how packages are installed, set by flags on the commands that install packages.
+#[derive(Clone, Default)]
+pub struct InstallOptions {
+    // Peer dependency conflicts fail the top level package instead of only being warned about.
+    pub strict_peer_dependencies: bool,
+    // Top level packages from the optionalDependencies of the package.json, they are skipped with a warning if they fail.
+    pub optional_packages: HashSet<String>,
+    // devDependencies in the package.json are left out, along with anything only they need.
+    pub omit_dev: bool,
+}
 
 #[derive(Default)]
 pub struct InstallHandler {
-    package_name: String,
-    semantic_version: Option<Comparator>, // If None then assume latest version.
+    // If this is empty, every dependency in the package.json will be installed.
+    packages: Vec<PackageDetails>,
+    options: InstallOptions,
 }
 
 impl InstallHandler {
-    fn write_lockfiles(dependency_map_mux: DependencyMapMutex) -> Result<(), CommandError> {
-        let dependency_map = dependency_map_mux.lock().unwrap();
+    /// Writes the lockfile of every package that installed successfully.
+    /// Incomplete packages are left without one so they aren't treated as cached next time.
+    /// The locks of overridden packages only apply to this project, so they go in the project lock rather than the cache.
+    /// Returns the dependencies that packages had in their previous lock but not anymore, such as the versions an override replaced.
+    fn write_lockfiles(
+        dependency_map: &DependencyMap,
+        incomplete_packages: &HashSet<String>,
+        overridden_packages: &HashSet<String>,
+    ) -> Result<HashSet<String>, CommandError> {
+        let mut replaced_dependencies = HashSet::new();
 
         for (package_name, package_lock) in dependency_map.iter() {
-            let mut package_lock_file = File::create(format!(
-                "{}/{}/package/click-lock.json",
-                *CACHE_DIRECTORY, package_name
-            ))
-            .map_err(CommandError::FailedToCreateFile)?;
+            if incomplete_packages.contains(package_name) {
+                continue;
+            }
+
+            if let Ok(previous_lock) = Cache::read_lockfile(package_name) {
+                replaced_dependencies.extend(
+                    previous_lock
+                        .dependencies
+                        .into_iter()
+                        .filter(|dependency| !package_lock.dependencies.contains(dependency)),
+                );
+            }
+
+            if overridden_packages.contains(package_name) {
+                Cache::set_overridden_lock(package_name, Some(package_lock.clone()));
+                continue;
+            }
+
+            Cache::set_overridden_lock(package_name, None);
+
+            let mut package_lock_file = File::create(Cache::lockfile_path(package_name))
+                .map_err(CommandError::FailedToCreateFile)?;
 
             let package_lock_string = serde_json::to_string(package_lock)
                 .map_err(CommandError::FailedToSerializePackageLock)?;
@@ -44,70 +93,638 @@ impl InstallHandler {
                 .map_err(CommandError::FailedToWriteFile)?;
         }
 
-        Ok(())
+        Ok(replaced_dependencies)
     }
-}
 
-#[async_trait]
-impl CommandHandler for InstallHandler {
-    fn parse(&mut self, args: &mut Args) -> Result<(), ParseError> {
-        let package_details = args
-            .next()
-            .ok_or(ParseError::MissingArgument(String::from("package name")))?;
+    /// Reads the dependencies, dev dependencies and optional dependencies from the package.json.
+    /// The names of the optional dependencies are returned too, as they are allowed to fail.
+    fn package_json_dependencies(
+        omit_dev: bool,
+    ) -> Result<(Vec<PackageDetails>, HashSet<String>), CommandError> {
+        let package_json = PackageJson::read()?;
+        let mut packages = Vec::new();
+        let mut optional_packages = HashSet::new();
 
-        let (package_name, semantic_version) =
-            Versions::parse_semantic_package_details(package_details)?;
-        self.package_name = package_name;
-        self.semantic_version = semantic_version;
+        for (package_name, (dependency_type, raw_version)) in package_json.typed_dependencies() {
+            if omit_dev && dependency_type == DependencyType::Dev {
+                continue;
+            }
 
-        Ok(())
-    }
+            let version_spec = Versions::parse_dependency_version(&raw_version).map_err(|err| {
+                CommandError::InvalidDependencyVersion(package_name.to_string(), err)
+            })?;
 
-    async fn execute(&self) -> Result<(), CommandError> {
-        // In future we could automatically find a version that is valid for both limits to save storage, but that's not neccessary right now
-        println!("Installing '{}'..", self.package_name);
+            if dependency_type == DependencyType::Optional {
+                optional_packages.insert(package_name.to_string());
+            }
 
-        let client = reqwest::Client::new();
-        let semantic_version = self.semantic_version.as_ref();
-        let full_version = Versions::resolve_full_version(semantic_version);
-        let full_version = full_version.as_ref();
+            packages.push((package_name, version_spec));
+        }
 
-        let (is_cached, cached_version) =
-            Cache::exists(&self.package_name, full_version, semantic_version).await?;
+        Ok((packages, optional_packages))
+    }
 
+    /// Installs the given packages and links them into the node modules folder.
+    /// A package failing to install does not stop the others, so a result is returned for each package in the same order they were provided.
+    /// Successfully installed packages are returned as their stringified version.
+    pub async fn install_packages(
+        packages: &[PackageDetails],
+        options: &InstallOptions,
+    ) -> Result<Vec<Result<String, CommandError>>, CommandError> {
+        let client = reqwest::Client::new();
         Installer::create_modules_dir();
 
-        if is_cached {
-            let version = cached_version.expect("Could not resolve version of cached package");
-            Cache::load_cached_version(Versions::stringify(&self.package_name, &version));
+        let overrides = Arc::new(Overrides::read()?);
 
-            return Ok(());
+        // Every package shares the same dependency map so common dependencies are only resolved and downloaded once.
+        let dependency_map_mux = Arc::new(Mutex::new(HashMap::new()));
+        let failure_map_mux = Arc::new(Mutex::new(HashMap::new()));
+        let linked_directories_mux = Arc::new(Mutex::new(Vec::new()));
+
+        let install_context = Self::install_context(
+            &client,
+            &dependency_map_mux,
+            &failure_map_mux,
+            &overrides,
+            &linked_directories_mux,
+        );
+        let mut results = Vec::new();
+
+        for package_details @ (package_name, version_spec) in packages.iter() {
+            // In future we could automatically find a version that is valid for both limits to save storage, but that's not neccessary right now
+            Logger::info(format!(
+                "Installing '{}'..",
+                Self::display_name(package_details)
+            ));
+
+            let result =
+                Self::install_top_level(install_context.clone(), package_name, version_spec).await;
+
+            results.push(result);
         }
 
-        let version_data = Installer::get_version_data(
-            client.clone(),
-            &self.package_name,
-            full_version,
-            semantic_version,
+        // The extraction thread only finishes once every sender has been dropped
+        drop(install_context);
+
+        // Blocks the main thread however it's not going to have a huge performance impact on tokio
+        TaskAllocator::block_until_done();
+
+        let peer_packages = Self::install_missing_peers(
+            &client,
+            &results,
+            &dependency_map_mux,
+            &failure_map_mux,
+            &overrides,
+            &linked_directories_mux,
         )
         .await?;
 
+        let mut dependency_map = dependency_map_mux.lock().unwrap();
+        let mut failure_map = failure_map_mux.lock().unwrap();
+        let mut incomplete_packages = Self::incomplete_packages(&dependency_map, &failure_map);
+
+        Self::drop_failed_optional_dependencies(
+            &mut dependency_map,
+            &incomplete_packages,
+            &failure_map,
+        );
+
+        // Linked packages only get their lockfiles once their dependencies are installed, the same goes for anything that depends on them
+        let linked_directories = linked_directories_mux.lock().unwrap();
+        let pending_packages =
+            Self::pending_packages(&dependency_map, &failure_map, &linked_directories);
+
+        let overridden_packages = overrides.overridden_packages();
+        let mut replaced_dependencies =
+            Self::write_lockfiles(&dependency_map, &pending_packages, &overridden_packages)?;
+
+        if !linked_directories.is_empty() {
+            failure_map.extend(Self::install_linked_dependencies(&linked_directories));
+
+            let all_incomplete_packages = Self::incomplete_packages(&dependency_map, &failure_map);
+            let failed_packages = all_incomplete_packages
+                .difference(&incomplete_packages)
+                .cloned()
+                .collect();
+
+            Self::drop_failed_optional_dependencies(
+                &mut dependency_map,
+                &failed_packages,
+                &failure_map,
+            );
+            incomplete_packages = all_incomplete_packages;
+
+            let pending_map = dependency_map
+                .iter()
+                .filter(|(package, _)| pending_packages.contains(*package))
+                .map(|(package, package_lock)| (package.to_string(), package_lock.clone()))
+                .collect();
+
+            replaced_dependencies.extend(Self::write_lockfiles(
+                &pending_map,
+                &incomplete_packages,
+                &overridden_packages,
+            )?);
+        }
+
+        let project_packages = Self::project_packages(&results, &peer_packages)?;
+        let mut reported_conflicts = HashSet::new();
+
+        let relinked_packages = Self::unlink_replaced_dependencies(&replaced_dependencies)?;
+
+        for (result, package_details) in results.iter_mut().zip(packages) {
+            if let Ok(stringified) = result {
+                let real_package = Versions::real_package(stringified);
+
+                if incomplete_packages.contains(&real_package) {
+                    let (failed_package, message) =
+                        Self::find_failure(&real_package, &dependency_map, &failure_map);
+
+                    *result = Err(CommandError::InstallFailed(failed_package, message));
+                }
+            }
+
+            if let Ok(stringified) = result {
+                let peer_result = Self::check_peer_conflicts(
+                    stringified,
+                    &dependency_map,
+                    &project_packages,
+                    options,
+                    &mut reported_conflicts,
+                );
+
+                if let Err(err) = peer_result {
+                    *result = Err(err);
+                }
+            }
+
+            match result {
+                Ok(stringified) => {
+                    Cache::load_cached_version(stringified.to_string());
+                    Logger::info(format!("Installed '{stringified}'"));
+                }
+                Err(err) if options.optional_packages.contains(&package_details.0) => {
+                    Installer::skip_optional_dependency(&package_details.0, None, err)
+                }
+                Err(err) => Logger::error(format!(
+                    "Failed to install '{}': {err}",
+                    Self::display_name(package_details)
+                )),
+            }
+        }
+
+        for peer_package in peer_packages.iter() {
+            let real_package = Versions::real_package(peer_package);
+
+            let result = match incomplete_packages.contains(&real_package) {
+                true => {
+                    let (failed_package, message) =
+                        Self::find_failure(&real_package, &dependency_map, &failure_map);
+                    Err(CommandError::InstallFailed(failed_package, message))
+                }
+                false => Self::check_peer_conflicts(
+                    peer_package,
+                    &dependency_map,
+                    &project_packages,
+                    options,
+                    &mut reported_conflicts,
+                ),
+            };
+
+            match result {
+                Ok(_) => {
+                    Cache::load_cached_version(peer_package.to_string());
+                    Logger::info(format!("Installed peer dependency '{peer_package}'"));
+                }
+                Err(err) => Logger::error(format!(
+                    "Failed to install peer dependency '{peer_package}': {err}"
+                )),
+            }
+        }
+
+        // Packages this install didn't link get back whatever was unlinked from under them
+        let linked_names = results
+            .iter()
+            .flatten()
+            .chain(&peer_packages)
+            .map(|stringified| Versions::parse_raw_package_details(stringified.to_string()).0)
+            .collect::<HashSet<_>>();
+
+        for (package_name, cached_package) in relinked_packages {
+            if !linked_names.contains(&package_name) {
+                Cache::load_cached_version(cached_package);
+            }
+        }
+
+        for applied_override in overrides.applied() {
+            Logger::info(applied_override);
+        }
+
+        let peer_names = peer_packages
+            .iter()
+            .map(|stringified| Versions::parse_raw_package_details(stringified.to_string()).0)
+            .collect::<Vec<_>>();
+        NodeModules::write_lock(&peer_names)?;
+
+        Ok(results)
+    }
+
+    /// The packages whose lockfiles have to wait for the dependencies of linked packages to be installed, as they could still fail.
+    /// Incomplete packages are included, along with the parents of any of these packages as they might lose an optional dependency.
+    fn pending_packages(
+        dependency_map: &DependencyMap,
+        failure_map: &HashMap<String, String>,
+        linked_directories: &[(String, PathBuf)],
+    ) -> HashSet<String> {
+        let mut pending_failures = failure_map.clone();
+        pending_failures.extend(
+            linked_directories
+                .iter()
+                .map(|(stringified, _)| (stringified.to_string(), String::new())),
+        );
+
+        let mut pending_packages = Self::incomplete_packages(dependency_map, &pending_failures);
+        let parent_packages = dependency_map
+            .iter()
+            .filter(|(_, package_lock)| {
+                package_lock.dependencies.iter().any(|dependency| {
+                    pending_packages.contains(&Versions::real_package(dependency))
+                })
+            })
+            .map(|(package, _)| package.to_string())
+            .collect::<Vec<_>>();
+
+        pending_packages.extend(parent_packages);
+        pending_packages
+    }
+
+    /// Installs the dependencies of linked packages into their own folders, the failures are returned in the same form as the failure map.
+    /// This is done by another click process sharing the cache, so it has to wait until this install's lockfiles are written.
+    /// Otherwise it would see the packages this install is extracting as missing, and extract them into the same folders at the same time.
+    fn install_linked_dependencies(
+        linked_directories: &[(String, PathBuf)],
+    ) -> HashMap<String, String> {
+        linked_directories
+            .iter()
+            .filter_map(|(stringified, directory)| {
+                Installer::install_in_directory(directory, &Config::get().cache_directory())
+                    .err()
+                    .map(|err| (stringified.to_string(), err.to_string()))
+            })
+            .collect()
+    }
+
+    /// Unlinks the dependencies that packages no longer have, if they are still linked at that version.
+    /// Linking never replaces a dependency that is already linked, so otherwise the old version would stay.
+    /// The top level packages that were linked are returned, they have to be linked again in case something they need was unlinked.
+    fn unlink_replaced_dependencies(
+        replaced_dependencies: &HashSet<String>,
+    ) -> Result<HashMap<String, String>, CommandError> {
+        if replaced_dependencies.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let linked_packages = NodeModules::linked_packages()?;
+        let top_level_packages = NodeModules::top_level_packages(&linked_packages)?;
+
+        for (package_name, linked_package) in linked_packages.iter() {
+            if replaced_dependencies.contains(linked_package) {
+                NodeModules::unlink(package_name)?;
+            }
+        }
+
+        Ok(linked_packages
+            .into_iter()
+            .filter(|(package_name, _)| top_level_packages.contains(package_name))
+            .collect())
+    }
+
+    /// Checks the peer dependencies of a top level package and everything below it.
+    /// Conflicts are warned about (once each), with strict peer dependencies the first one is returned so the package fails instead.
+    fn check_peer_conflicts(
+        stringified: &str,
+        dependency_map: &DependencyMap,
+        project_packages: &HashMap<String, String>,
+        options: &InstallOptions,
+        reported_conflicts: &mut HashSet<String>,
+    ) -> Result<(), CommandError> {
+        let conflicts = Peers::check(stringified, dependency_map, project_packages).conflicts;
+
+        for conflict in conflicts {
+            if options.strict_peer_dependencies {
+                return Err(conflict);
+            }
+
+            if reported_conflicts.insert(conflict.to_string()) {
+                Logger::warn(conflict.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts the thread that extracts downloaded packages into the cache, the returned context sends packages to it.
+    /// The thread finishes once the context (and every clone of it) has been dropped.
+    fn install_context(
+        client: &reqwest::Client,
+        dependency_map_mux: &DependencyMapMutex,
+        failure_map_mux: &FailureMapMutex,
+        overrides: &Arc<Overrides>,
+        linked_directories_mux: &LinkedDirectoriesMutex,
+    ) -> InstallContext {
         let (bytes_sender, bytes_receiver) = channel::<PackageBytes>();
+        let extraction_failure_map_mux = Arc::clone(failure_map_mux);
 
         TaskAllocator::add_blocking(move || {
-            while let Ok((package_dest, bytes)) = bytes_receiver.recv() {
-                util::extract_tarball(bytes, package_dest).unwrap();
+            while let Ok((stringified, bytes)) = bytes_receiver.recv() {
+                let package_dest = Cache::package_directory(&stringified);
+
+                // Anything left from an earlier install (a failed one, or an older copy of a local package) would be mixed in with the new files
+                if Path::new(&package_dest).exists() {
+                    if let Err(err) = fs::remove_dir_all(&package_dest) {
+                        let err = CommandError::FailedToRemoveFile(err);
+                        Installer::record_failure(&extraction_failure_map_mux, &stringified, err);
+                        continue;
+                    }
+                }
+
+                if let Err(err) = util::extract_tarball(bytes, package_dest) {
+                    Installer::record_failure(&extraction_failure_map_mux, &stringified, err);
+                }
             }
         });
 
-        let dependency_map_mux = Arc::new(Mutex::new(HashMap::new()));
-
-        let install_context = InstallContext {
-            client,
+        InstallContext {
+            client: client.clone(),
             bytes_sender,
-            dependency_map_mux: Arc::clone(&dependency_map_mux),
-        };
+            dependency_map_mux: Arc::clone(dependency_map_mux),
+            failure_map_mux: Arc::clone(failure_map_mux),
+            overrides: Arc::clone(overrides),
+            linked_directories_mux: Arc::clone(linked_directories_mux),
+        }
+    }
+
+    /// Installs the peer dependencies that the parent of the package needing them doesn't provide, as npm does.
+    /// Peers can have peers of their own, so this is repeated until nothing is missing.
+    /// Peers missing from the project are returned, they are linked like any other top level package but aren't saved to the package.json.
+    async fn install_missing_peers(
+        client: &reqwest::Client,
+        results: &[Result<String, CommandError>],
+        dependency_map_mux: &DependencyMapMutex,
+        failure_map_mux: &FailureMapMutex,
+        overrides: &Arc<Overrides>,
+        linked_directories_mux: &LinkedDirectoriesMutex,
+    ) -> Result<Vec<String>, CommandError> {
+        let mut peer_packages = Vec::new();
 
+        // Each peer is only tried once for each parent, otherwise one that fails would be retried forever
+        let mut attempted_peers = HashSet::new();
+
+        loop {
+            let project_packages = Self::project_packages(results, &peer_packages)?;
+
+            let missing_peers = {
+                let dependency_map = dependency_map_mux.lock().unwrap();
+
+                // Peers that were installed for the project can be missing peers of their own
+                results
+                    .iter()
+                    .flatten()
+                    .chain(&peer_packages)
+                    .flat_map(|root| Peers::check(root, &dependency_map, &project_packages).missing)
+                    .filter(|missing_peer| {
+                        attempted_peers
+                            .insert((missing_peer.parent.clone(), missing_peer.name.clone()))
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            if missing_peers.is_empty() {
+                return Ok(peer_packages);
+            }
+
+            let install_context = Self::install_context(
+                client,
+                dependency_map_mux,
+                failure_map_mux,
+                overrides,
+                linked_directories_mux,
+            );
+
+            for MissingPeer {
+                parent,
+                name,
+                range,
+            } in missing_peers
+            {
+                Logger::info(format!("Installing peer dependency '{name}@{range}'.."));
+
+                let Some(parent) = parent else {
+                    let result = match Versions::parse_dependency_version(&range) {
+                        Ok(version_spec) => {
+                            Self::install_top_level(install_context.clone(), &name, &version_spec)
+                                .await
+                        }
+                        Err(err) => Err(CommandError::InvalidDependencyVersion(
+                            name.to_string(),
+                            err,
+                        )),
+                    };
+
+                    match result {
+                        Ok(stringified) => peer_packages.push(stringified),
+                        Err(err) => Logger::error(format!(
+                            "Failed to install peer dependency '{name}@{range}': {err}"
+                        )),
+                    }
+                    continue;
+                };
+
+                Installer::install_peer_dependency(&parent, &install_context, &name, &range).await;
+            }
+
+            drop(install_context);
+            TaskAllocator::block_until_done();
+        }
+    }
+
+    /// Everything the project provides to the peer dependencies of its top level packages, keyed by the name it's linked under.
+    /// The packages this install is about to link replace whatever is linked under the same name.
+    fn project_packages(
+        results: &[Result<String, CommandError>],
+        peer_packages: &[String],
+    ) -> Result<HashMap<String, String>, CommandError> {
+        let mut project_packages = NodeModules::linked_packages()?;
+
+        for stringified in results.iter().flatten().chain(peer_packages) {
+            let (package_name, _) = Versions::parse_raw_package_details(stringified.to_string());
+            project_packages.insert(package_name, stringified.to_string());
+        }
+
+        Ok(project_packages
+            .into_iter()
+            .map(|(package_name, package)| (package_name, Versions::real_package(&package)))
+            .collect())
+    }
+
+    /// Returns every package in the dependency map that failed or has a dependency somewhere below it that failed.
+    /// Aliases are stripped from the dependencies, as the dependency map and failures are keyed by the package that was actually installed.
+    fn incomplete_packages(
+        dependency_map: &DependencyMap,
+        failure_map: &HashMap<String, String>,
+    ) -> HashSet<String> {
+        let mut dependents: HashMap<String, Vec<&String>> = HashMap::new();
+        for (package, package_lock) in dependency_map.iter() {
+            for dependency in package_lock.dependencies.iter() {
+                // A package works without its optional dependencies, so it isn't incomplete if one of them failed
+                if package_lock.is_optional(dependency) {
+                    continue;
+                }
+
+                dependents
+                    .entry(Versions::real_package(dependency))
+                    .or_default()
+                    .push(package);
+            }
+        }
+
+        let mut incomplete_packages = HashSet::new();
+        let mut pending = failure_map.keys().collect::<Vec<_>>();
+
+        while let Some(package) = pending.pop() {
+            if !incomplete_packages.insert(package.to_string()) {
+                continue;
+            }
+
+            pending.extend(dependents.get(package).cloned().unwrap_or_default());
+        }
+
+        incomplete_packages
+    }
+
+    /// Removes the optional dependencies that failed to install from the lock of their parent, so it's linked without them.
+    fn drop_failed_optional_dependencies(
+        dependency_map: &mut DependencyMap,
+        incomplete_packages: &HashSet<String>,
+        failure_map: &HashMap<String, String>,
+    ) {
+        let mut failed_dependencies = Vec::new();
+
+        for (package, package_lock) in dependency_map.iter() {
+            for dependency in package_lock.dependencies.iter() {
+                let real_package = Versions::real_package(dependency);
+
+                if package_lock.is_optional(dependency)
+                    && incomplete_packages.contains(&real_package)
+                {
+                    failed_dependencies.push((package.to_string(), dependency.to_string()));
+                }
+            }
+        }
+
+        for (package, dependency) in failed_dependencies {
+            let (failed_package, message) = Self::find_failure(
+                &Versions::real_package(&dependency),
+                dependency_map,
+                failure_map,
+            );
+            let err = CommandError::InstallFailed(failed_package, message);
+            Installer::skip_optional_dependency(&dependency, Some(&package), &err);
+
+            if let Some(package_lock) = dependency_map.get_mut(&package) {
+                let (dependency_name, _) = Versions::parse_raw_package_details(dependency.clone());
+
+                package_lock
+                    .dependencies
+                    .retain(|locked| locked != &dependency);
+                package_lock.ranges.remove(&dependency_name);
+            }
+        }
+    }
+
+    /// Finds the package (and its error message) that caused an incomplete package to fail.
+    fn find_failure(
+        package: &String,
+        dependency_map: &DependencyMap,
+        failure_map: &HashMap<String, String>,
+    ) -> (String, String) {
+        let mut visited = HashSet::new();
+        let mut pending = vec![package.to_string()];
+
+        while let Some(package) = pending.pop() {
+            if let Some(message) = failure_map.get(&package) {
+                return (package, message.to_string());
+            }
+
+            if !visited.insert(package.to_string()) {
+                continue;
+            }
+
+            if let Some(package_lock) = dependency_map.get(&package) {
+                pending.extend(
+                    package_lock
+                        .dependencies
+                        .iter()
+                        .map(|dependency| Versions::real_package(dependency)),
+                );
+            }
+        }
+
+        (package.to_string(), String::from("unknown error"))
+    }
+
+    /// Resolves a single top level package and queues it (and its dependencies) to be installed.
+    /// Returns the stringified version of the package which needs to be linked once everything is installed,
+    /// aliased packages are returned as alias@npm:package@version so they are linked under their alias.
+    async fn install_top_level(
+        install_context: InstallContext,
+        package_name: &str,
+        version_spec: &VersionSpec,
+    ) -> Result<String, CommandError> {
+        let (real_name, version_spec) = version_spec.real_package(package_name);
+        let real_name = real_name.to_string();
+
+        match version_spec {
+            VersionSpec::Git(git_spec) => {
+                let version_data = Installer::get_git_version_data(git_spec).await?;
+                return Self::install_resolved(install_context, package_name, version_data);
+            }
+            // Paths in the package.json are relative to the project
+            VersionSpec::Local(local_spec) => {
+                let version_data = Local::version_data(local_spec, Path::new("."))?;
+                return Self::install_resolved(install_context, package_name, version_data);
+            }
+            VersionSpec::Remote(remote_spec) => {
+                let version_data =
+                    Remote::version_data(install_context.client.clone(), remote_spec).await?;
+                return Self::install_resolved(install_context, package_name, version_data);
+            }
+            _ => {}
+        }
+
+        let full_version = Versions::resolve_full_version(version_spec);
+        let full_version = full_version.as_ref();
+
+        let (is_cached, cached_version) =
+            Cache::exists(&real_name, full_version, version_spec).await?;
+
+        if is_cached {
+            let version = cached_version.expect("Could not resolve version of cached package");
+            let stringified = Versions::stringify(&real_name, &version);
+
+            Installer::apply_overrides(&install_context, &stringified);
+            return Ok(Versions::stringify_alias(package_name, &stringified));
+        }
+
+        let version_data = Installer::get_version_data(
+            install_context.client.clone(),
+            &real_name,
+            full_version,
+            version_spec,
+        )
+        .await?;
+
+        Platform::check(&version_data)?;
         let stringified = Versions::stringify(&version_data.name, &version_data.version);
 
         let package_info = PackageInfo {
@@ -116,18 +733,101 @@ impl CommandHandler for InstallHandler {
             stringified: stringified.to_string(),
         };
 
-        Installer::install_package(
-            install_context,
-            package_info,
-            Arc::new(Mutex::new(Vec::new())),
-        )?;
+        Installer::install_package(install_context, package_info)?;
 
-        // Blocks the main thread however it's not going to have a huge performance impact on tokio
-        TaskAllocator::block_until_done();
+        Ok(Versions::stringify_alias(package_name, &stringified))
+    }
 
-        Self::write_lockfiles(dependency_map_mux)?;
-        Cache::load_cached_version(stringified);
+    /// Same as install_top_level() for git, local and remote packages, which can only be looked up in the cache once they are resolved.
+    /// Packages installed without a name (click add github:user/repo) are linked under the name in their package.json.
+    fn install_resolved(
+        install_context: InstallContext,
+        package_name: &str,
+        version_data: VersionData,
+    ) -> Result<String, CommandError> {
+        Platform::check(&version_data)?;
+        let stringified = Versions::stringify(&version_data.name, &version_data.version);
+
+        let package_name = match package_name.is_empty() {
+            true => version_data.name.to_string(),
+            false => package_name.to_string(),
+        };
+
+        match Cache::is_installed(&stringified) {
+            true => Installer::apply_overrides(&install_context, &stringified),
+            false => {
+                let package_info = PackageInfo {
+                    version_data,
+                    is_latest: false,
+                    stringified: stringified.to_string(),
+                };
+
+                Installer::install_package(install_context, package_info)?;
+            }
+        }
+
+        Ok(Versions::stringify_alias(&package_name, &stringified))
+    }
+
+    /// The name a package is reported with while it's being installed, git, local and remote packages don't have one until they are resolved.
+    fn display_name((package_name, version_spec): &PackageDetails) -> String {
+        match package_name.is_empty() {
+            true => version_spec.to_string(),
+            false => package_name.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for InstallHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--strict-peer-dependencies" => self.options.strict_peer_dependencies = true,
+                "--production" => self.options.omit_dev = true,
+                "--omit" => {
+                    let omitted = args
+                        .next()
+                        .ok_or(ParseError::MissingArgument(String::from("dependency type")))?;
+
+                    match omitted.as_str() {
+                        "dev" => self.options.omit_dev = true,
+                        _ => return Err(ParseError::InvalidArgument(arg, omitted)),
+                    }
+                }
+                _ if arg.starts_with('-') => return Err(ParseError::UnknownFlag(arg)),
+                _ => self
+                    .packages
+                    .push(Versions::parse_semantic_package_details(arg)?),
+            }
+        }
 
         Ok(())
     }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let (packages, optional_packages) = match self.packages.is_empty() {
+            true => Self::package_json_dependencies(self.options.omit_dev)?,
+            false => (self.packages.clone(), HashSet::new()),
+        };
+
+        let options = InstallOptions {
+            optional_packages,
+            ..self.options.clone()
+        };
+
+        let results = Self::install_packages(&packages, &options).await?;
+        let failed_count = results
+            .iter()
+            .zip(&packages)
+            .filter(|(result, (package_name, _))| {
+                result.is_err() && !options.optional_packages.contains(package_name)
+            })
+            .count();
+
+        match failed_count {
+            0 => Ok(()),
+            _ => Err(CommandError::PackagesFailed(failed_count)),
+        }
+    }
 }
@@ -0,0 +1,398 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::Path,
+};
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    cache::Cache,
+    config::Config,
+    errors::{CommandError, ParseError},
+    node_modules::NodeModules,
+    package_json::{DependencyType, PackageJson, PACKAGE_JSON_PATH},
+    versions::Versions,
+};
+
+use super::command_handler::{CommandArgs, CommandHandler};
+
+/// Prints the installed dependency tree, built from the package locks of every linked package.
+#[derive(Default)]
+pub struct ListHandler {
+    package_name: Option<String>,
+    depth: Option<usize>,
+    dependency_types: Option<Vec<DependencyType>>,
+    parseable: bool,
+}
+
+/// Every package reachable from the root packages mapped to its dependencies, read from the package locks.
+struct DependencyTree {
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl DependencyTree {
+    fn load(root_packages: &[(String, Option<String>)]) -> Result<Self, CommandError> {
+        let mut dependencies = HashMap::new();
+        let mut pending = root_packages
+            .iter()
+            .filter_map(|(_, cached_package)| cached_package.clone())
+            .collect::<Vec<_>>();
+
+        while let Some(package) = pending.pop() {
+            if dependencies.contains_key(&package) {
+                continue;
+            }
+
+            let mut package_dependencies = Cache::read_lockfile(&package)?.dependencies;
+            package_dependencies.sort();
+
+            pending.extend(package_dependencies.clone());
+            dependencies.insert(package, package_dependencies);
+        }
+
+        Ok(Self { dependencies })
+    }
+
+    fn dependencies(&self, package: &String) -> &[String] {
+        self.dependencies
+            .get(package)
+            .map(|dependencies| dependencies.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns every package which is named `package_name` or has it somewhere in its dependencies.
+    fn leading_to(&self, package_name: &str) -> HashSet<String> {
+        let mut dependents: HashMap<&String, Vec<&String>> = HashMap::new();
+        for (package, dependencies) in self.dependencies.iter() {
+            for dependency in dependencies {
+                dependents.entry(dependency).or_default().push(package);
+            }
+        }
+
+        let mut leading_to = HashSet::new();
+        let mut pending = self
+            .dependencies
+            .keys()
+            .filter(|package| {
+                Versions::parse_raw_package_details(package.to_string()).0 == package_name
+            })
+            .collect::<Vec<_>>();
+
+        while let Some(package) = pending.pop() {
+            if !leading_to.insert(package.to_string()) {
+                continue;
+            }
+
+            pending.extend(dependents.get(package).cloned().unwrap_or_default());
+        }
+
+        leading_to
+    }
+}
+
+impl ListHandler {
+    /// The direct dependencies of the project which are included by the filters, mapped to what they are linked to (if anything).
+    fn root_packages(&self) -> Result<Vec<(String, Option<String>)>, CommandError> {
+        let linked_packages = NodeModules::linked_packages()?;
+
+        if !Path::new(PACKAGE_JSON_PATH).exists() {
+            let mut root_packages = NodeModules::top_level_packages(&linked_packages)?
+                .into_iter()
+                .map(|package_name| {
+                    let cached_package = linked_packages.get(&package_name).cloned();
+                    (package_name, cached_package)
+                })
+                .collect::<Vec<_>>();
+
+            root_packages.sort();
+            return Ok(root_packages);
+        }
+
+        let dependency_types = self
+            .dependency_types
+            .clone()
+            .unwrap_or(DependencyType::INSTALLABLE.to_vec());
+
+        let mut root_packages = PackageJson::read()?
+            .typed_dependencies()
+            .into_iter()
+            .filter(|(_, (dependency_type, _))| dependency_types.contains(dependency_type))
+            .map(|(package_name, _)| {
+                let cached_package = linked_packages.get(&package_name).cloned();
+                (package_name, cached_package)
+            })
+            .collect::<Vec<_>>();
+
+        root_packages.sort();
+        Ok(root_packages)
+    }
+
+    fn print_header() -> Result<(), CommandError> {
+        let working_directory = env::current_dir().map_err(CommandError::NoWorkingDirectory)?;
+
+        let package_json = match Path::new(PACKAGE_JSON_PATH).exists() {
+            true => PackageJson::read()?,
+            false => PackageJson::default(),
+        };
+
+        match (package_json.name, package_json.version) {
+            (Some(name), Some(version)) => {
+                println!("{name}@{version} {}", working_directory.display())
+            }
+            (Some(name), None) => println!("{name} {}", working_directory.display()),
+            _ => println!("{}", working_directory.display()),
+        }
+
+        Ok(())
+    }
+
+    fn print_tree(&self, root_packages: Vec<(String, Option<String>)>) -> Result<(), CommandError> {
+        let tree = DependencyTree::load(&root_packages)?;
+        let mut printer = TreePrinter::new(&tree, self);
+
+        Self::print_header()?;
+
+        let rows = root_packages
+            .into_iter()
+            .filter_map(|(package_name, cached_package)| match cached_package {
+                Some(cached_package) if printer.is_visible(&cached_package) => {
+                    Some((cached_package, true))
+                }
+                None if printer.visible.is_none() => {
+                    Some((format!("{package_name} missing"), false))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (index, (row, is_installed)) in rows.iter().enumerate() {
+            let is_last = index == rows.len() - 1;
+
+            match is_installed {
+                true => printer.print_package(row, "", is_last, 0),
+                false => println!("{}{row}", TreePrinter::branch(is_last)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the tree in the same shape as `npm ls --json`.
+    fn print_json(&self, root_packages: Vec<(String, Option<String>)>) -> Result<(), CommandError> {
+        let tree = DependencyTree::load(&root_packages)?;
+        let mut printer = TreePrinter::new(&tree, self);
+
+        let package_json = match Path::new(PACKAGE_JSON_PATH).exists() {
+            true => PackageJson::read()?,
+            false => PackageJson::default(),
+        };
+
+        let mut json = Map::new();
+        if let Some(name) = package_json.name {
+            json.insert(String::from("name"), Value::String(name));
+        }
+        if let Some(version) = package_json.version {
+            json.insert(String::from("version"), Value::String(version));
+        }
+
+        let mut dependencies = Map::new();
+        for (package_name, cached_package) in root_packages {
+            match cached_package {
+                Some(cached_package) if printer.is_visible(&cached_package) => {
+                    dependencies.insert(package_name, printer.package_json(&cached_package, 0));
+                }
+                None if printer.visible.is_none() => {
+                    dependencies.insert(package_name, json!({ "missing": true }));
+                }
+                _ => continue,
+            }
+        }
+
+        json.insert(String::from("dependencies"), Value::Object(dependencies));
+
+        let json = serde_json::to_string_pretty(&Value::Object(json))
+            .map_err(CommandError::FailedToSerializeOutput)?;
+
+        println!("{json}");
+        Ok(())
+    }
+
+    /// Prints every package in the tree once per line, formatted as package@version.
+    fn print_parseable(
+        &self,
+        root_packages: Vec<(String, Option<String>)>,
+    ) -> Result<(), CommandError> {
+        let tree = DependencyTree::load(&root_packages)?;
+        let mut printer = TreePrinter::new(&tree, self);
+
+        let mut pending = root_packages
+            .into_iter()
+            .rev()
+            .filter_map(|(_, cached_package)| cached_package.map(|package| (package, 0)))
+            .collect::<Vec<_>>();
+
+        while let Some((package, depth)) = pending.pop() {
+            if !printer.is_visible(&package) || !printer.printed.insert(package.to_string()) {
+                continue;
+            }
+
+            println!("{package}");
+
+            if printer.is_max_depth(depth) {
+                continue;
+            }
+
+            for dependency in tree.dependencies(&package).iter().rev() {
+                pending.push((dependency.to_string(), depth + 1));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct TreePrinter<'a> {
+    tree: &'a DependencyTree,
+    // When searching for a package, only the packages that lead to it are visible.
+    visible: Option<HashSet<String>>,
+    printed: HashSet<String>,
+    max_depth: Option<usize>,
+}
+
+impl<'a> TreePrinter<'a> {
+    fn new(tree: &'a DependencyTree, list_handler: &ListHandler) -> Self {
+        Self {
+            tree,
+            visible: list_handler
+                .package_name
+                .as_ref()
+                .map(|package_name| tree.leading_to(package_name)),
+            printed: HashSet::new(),
+            max_depth: list_handler.depth,
+        }
+    }
+
+    fn is_visible(&self, package: &String) -> bool {
+        match &self.visible {
+            Some(visible) => visible.contains(package),
+            None => true,
+        }
+    }
+
+    fn is_max_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max_depth| depth >= max_depth)
+    }
+
+    fn branch(is_last: bool) -> &'static str {
+        if is_last {
+            "└── "
+        } else {
+            "├── "
+        }
+    }
+
+    /// Same as print_package() but builds a json object instead of printing.
+    fn package_json(&mut self, package: &String, depth: usize) -> Value {
+        let (_, version) = Versions::parse_raw_package_details(Versions::real_package(package));
+        let mut json = Map::new();
+        json.insert(String::from("version"), Value::String(version));
+
+        if !self.printed.insert(package.to_string()) {
+            json.insert(String::from("deduped"), Value::Bool(true));
+            return Value::Object(json);
+        }
+
+        if self.is_max_depth(depth) {
+            return Value::Object(json);
+        }
+
+        let mut dependencies = Map::new();
+        for dependency in self.tree.dependencies(package) {
+            if !self.is_visible(dependency) {
+                continue;
+            }
+
+            let (dependency_name, _) = Versions::parse_raw_package_details(dependency.to_string());
+            dependencies.insert(dependency_name, self.package_json(dependency, depth + 1));
+        }
+
+        if !dependencies.is_empty() {
+            json.insert(String::from("dependencies"), Value::Object(dependencies));
+        }
+
+        Value::Object(json)
+    }
+
+    /// Prints a package and its dependencies with the box drawing characters npm uses.
+    /// Packages that have already been printed are marked as deduped instead of printing their dependencies again.
+    fn print_package(&mut self, package: &String, prefix: &str, is_last: bool, depth: usize) {
+        let branch = Self::branch(is_last);
+        let is_deduped = !self.printed.insert(package.to_string());
+
+        match is_deduped {
+            true => println!("{prefix}{branch}{package} deduped"),
+            false => println!("{prefix}{branch}{package}"),
+        }
+
+        if is_deduped || self.is_max_depth(depth) {
+            return;
+        }
+
+        let dependencies = self
+            .tree
+            .dependencies(package)
+            .iter()
+            .filter(|dependency| self.is_visible(dependency))
+            .collect::<Vec<_>>();
+
+        let child_prefix = format!("{prefix}{}", if is_last { "    " } else { "│   " });
+
+        for (index, dependency) in dependencies.iter().enumerate() {
+            let is_last = index == dependencies.len() - 1;
+            self.print_package(dependency, &child_prefix, is_last, depth + 1);
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ListHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--depth" => {
+                    let depth = args
+                        .next()
+                        .ok_or(ParseError::MissingArgument(String::from("depth")))?;
+
+                    self.depth = Some(
+                        depth
+                            .parse::<usize>()
+                            .map_err(|_| ParseError::InvalidArgument(arg, depth))?,
+                    );
+                }
+                "--prod" | "--production" => {
+                    self.dependency_types =
+                        Some(vec![DependencyType::Regular, DependencyType::Optional])
+                }
+                "--dev" => self.dependency_types = Some(vec![DependencyType::Dev]),
+                "--parseable" => self.parseable = true,
+                _ if arg.starts_with('-') => return Err(ParseError::UnknownFlag(arg)),
+                _ => self.package_name = Some(arg),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let root_packages = self.root_packages()?;
+
+        match (Config::get().json, self.parseable) {
+            (true, _) => self.print_json(root_packages),
+            (false, true) => self.print_parseable(root_packages),
+            (false, false) => self.print_tree(root_packages),
+        }
+    }
+}
@@ -1,2 +1,11 @@
+pub mod add;
+pub mod cli;
 pub mod command_handler;
+pub mod init;
 pub mod install;
+pub mod list;
+pub mod outdated;
+pub mod prune;
+pub mod remove;
+pub mod update;
+pub mod why;
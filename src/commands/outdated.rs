@@ -0,0 +1,199 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    config::Config,
+    errors::{CommandError, ParseError},
+    installer::Installer,
+    logger::Logger,
+    node_modules::NodeModules,
+    package_json::PackageJson,
+    util::TaskAllocator,
+    versions::Versions,
+};
+
+use super::command_handler::{CommandArgs, CommandHandler};
+
+const MISSING: &str = "MISSING";
+
+#[derive(Serialize)]
+struct OutdatedPackage {
+    #[serde(skip)]
+    package_name: String,
+    current: Option<String>,
+    wanted: String,
+    latest: String,
+    #[serde(rename = "type")]
+    dependency_type: &'static str,
+}
+
+/// Reports dependencies where the installed version is not the latest, exits with a status of 1 if there are any.
+#[derive(Default)]
+pub struct OutdatedHandler;
+
+impl OutdatedHandler {
+    /// Returns the outdated packages along with how many dependencies could not be checked.
+    async fn find_outdated() -> Result<(Vec<OutdatedPackage>, usize), CommandError> {
+        let dependencies = PackageJson::read()?.typed_dependencies();
+        let linked_packages = NodeModules::linked_packages()?;
+        let client = reqwest::Client::new();
+
+        let mut tasks = Vec::new();
+        let mut failed_count = 0;
+
+        // Every package requires its own requests so they are all done at the same time
+        for (package_name, (dependency_type, raw_version)) in dependencies {
+            let version_spec = match Versions::parse_dependency_version(&raw_version) {
+                Ok(version_spec) => version_spec,
+                Err(err) => {
+                    let err = CommandError::InvalidDependencyVersion(package_name.to_string(), err);
+                    Logger::error(format!("Failed to check '{package_name}': {err}"));
+                    failed_count += 1;
+                    continue;
+                }
+            };
+
+            // Git and local packages follow their repository or folder rather than the registry
+            if !version_spec.is_registry() {
+                continue;
+            }
+
+            let current = linked_packages.get(&package_name).map(|cached_package| {
+                Versions::parse_raw_package_details(Versions::real_package(cached_package)).1
+            });
+
+            let client = client.clone();
+
+            tasks.push(TaskAllocator::add_task(async move {
+                let (real_name, real_version_spec) = version_spec.real_package(&package_name);
+
+                let resolved = async {
+                    let wanted = Installer::resolve_newest_version(
+                        client.clone(),
+                        real_name,
+                        real_version_spec,
+                    )
+                    .await?;
+                    let latest = Installer::resolve_latest_version(client, real_name).await?;
+
+                    Ok::<_, CommandError>((wanted, latest))
+                }
+                .await;
+
+                match resolved {
+                    Ok((wanted, latest)) => Ok(OutdatedPackage {
+                        package_name,
+                        current,
+                        wanted,
+                        latest,
+                        dependency_type: dependency_type.field_name(),
+                    }),
+                    Err(err) => Err((package_name, err)),
+                }
+            }));
+        }
+
+        let mut outdated_packages = Vec::new();
+
+        for task in tasks {
+            let package = match task.await.expect("Failed to join outdated task") {
+                Ok(package) => package,
+                Err((package_name, err)) => {
+                    // One unresolvable dependency shouldn't hide the rest of the report
+                    Logger::error(format!("Failed to check '{package_name}': {err}"));
+                    failed_count += 1;
+                    continue;
+                }
+            };
+
+            if package.current.as_ref() != Some(&package.latest) {
+                outdated_packages.push(package);
+            }
+        }
+
+        outdated_packages.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+        Ok((outdated_packages, failed_count))
+    }
+
+    fn print_json(outdated_packages: &[OutdatedPackage]) -> Result<(), CommandError> {
+        let mut json = Map::new();
+
+        for package in outdated_packages {
+            let package_json =
+                serde_json::to_value(package).map_err(CommandError::FailedToSerializeOutput)?;
+            json.insert(package.package_name.to_string(), package_json);
+        }
+
+        let json = serde_json::to_string_pretty(&Value::Object(json))
+            .map_err(CommandError::FailedToSerializeOutput)?;
+
+        println!("{json}");
+        Ok(())
+    }
+
+    fn print_table(outdated_packages: &[OutdatedPackage]) {
+        let mut rows = vec![[
+            "Package".to_string(),
+            "Current".to_string(),
+            "Wanted".to_string(),
+            "Latest".to_string(),
+            "Type".to_string(),
+        ]];
+
+        for package in outdated_packages {
+            rows.push([
+                package.package_name.to_string(),
+                package.current.clone().unwrap_or(MISSING.to_string()),
+                package.wanted.to_string(),
+                package.latest.to_string(),
+                package.dependency_type.to_string(),
+            ]);
+        }
+
+        let mut column_widths = [0; 5];
+        for row in rows.iter() {
+            for (column, cell) in row.iter().enumerate() {
+                column_widths[column] = column_widths[column].max(cell.len());
+            }
+        }
+
+        for row in rows {
+            let line = row
+                .iter()
+                .zip(column_widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for OutdatedHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        match args.next() {
+            Some(arg) => Err(ParseError::UnknownFlag(arg)),
+            None => Ok(()),
+        }
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let (outdated_packages, failed_count) = Self::find_outdated().await?;
+
+        match Config::get().json {
+            true => Self::print_json(&outdated_packages)?,
+            false if outdated_packages.is_empty() => println!("Everything is up to date"),
+            false => Self::print_table(&outdated_packages),
+        }
+
+        // A failing status lets CI flag stale dependencies
+        match (failed_count, outdated_packages.len()) {
+            (0, 0) => Ok(()),
+            (0, outdated_count) => Err(CommandError::OutdatedDependencies(outdated_count)),
+            (failed_count, _) => Err(CommandError::OutdatedCheckFailed(failed_count)),
+        }
+    }
+}
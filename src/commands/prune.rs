@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+
+use crate::{
+    errors::{CommandError, ParseError},
+    logger::Logger,
+    node_modules::NodeModules,
+};
+
+use super::command_handler::{CommandArgs, CommandHandler};
+
+/// Removes packages from the node modules folder that nothing in the package.json needs anymore.
+#[derive(Default)]
+pub struct PruneHandler {
+    // Packages the project lock marks as only needed by devDependencies are removed too.
+    production: bool,
+}
+
+#[async_trait]
+impl CommandHandler for PruneHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        for arg in args {
+            match arg.as_str() {
+                "--production" => self.production = true,
+                _ => return Err(ParseError::UnknownFlag(arg)),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let linked_packages = NodeModules::linked_packages()?;
+
+        let root_packages = NodeModules::root_packages(&linked_packages, &[])?;
+        let required_packages = NodeModules::required_packages(&linked_packages, &root_packages)?;
+        let dev_only = match self.production {
+            true => NodeModules::read_lock()?.dev_only,
+            false => Vec::new(),
+        };
+
+        let mut pruned_count = 0;
+
+        for package_name in linked_packages.keys() {
+            if required_packages.contains(package_name) && !dev_only.contains(package_name) {
+                continue;
+            }
+
+            NodeModules::unlink(package_name)?;
+            Logger::verbose(format!("Removed '{package_name}'"));
+            pruned_count += 1;
+        }
+
+        match pruned_count {
+            0 => Logger::info("Nothing to prune"),
+            _ => Logger::info(format!(
+                "Removed {pruned_count} packages that are no longer needed"
+            )),
+        }
+
+        NodeModules::write_lock(&[])
+    }
+}
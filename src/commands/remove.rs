@@ -0,0 +1,105 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    errors::{CommandError, ParseError},
+    logger::Logger,
+    node_modules::NodeModules,
+    package_json::{PackageJsonEditor, PACKAGE_JSON_PATH},
+};
+
+use super::command_handler::{CommandArgs, CommandHandler};
+
+/// Removes packages from the package.json and node modules, along with any dependencies nothing else needs.
+#[derive(Default)]
+pub struct RemoveHandler {
+    package_names: Vec<String>,
+}
+
+impl RemoveHandler {
+    /// The packages which should still be in the node modules folder once the removed packages are gone.
+    fn required_packages(
+        &self,
+        linked_packages: &HashMap<String, String>,
+    ) -> Result<HashSet<String>, CommandError> {
+        let root_packages = NodeModules::root_packages(linked_packages, &self.package_names)?;
+        NodeModules::required_packages(linked_packages, &root_packages)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for RemoveHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        for arg in args {
+            if arg.starts_with('-') {
+                return Err(ParseError::UnknownFlag(arg));
+            }
+
+            self.package_names.push(arg);
+        }
+
+        if self.package_names.is_empty() {
+            return Err(ParseError::MissingArgument(String::from("package name")));
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let linked_packages = NodeModules::linked_packages()?;
+
+        // The lockfiles are all read before anything is touched, so a broken cache can't leave things half removed
+        let required_packages = self.required_packages(&linked_packages)?;
+
+        let mut declared_packages = Vec::new();
+
+        if Path::new(PACKAGE_JSON_PATH).exists() {
+            let mut package_json = PackageJsonEditor::open()?;
+
+            for package_name in self.package_names.iter() {
+                if package_json.remove_dependency(package_name) {
+                    declared_packages.push(package_name);
+                }
+            }
+
+            if !declared_packages.is_empty() {
+                package_json.save()?;
+            }
+        }
+
+        for package_name in self.package_names.iter() {
+            if !declared_packages.contains(&package_name)
+                && !linked_packages.contains_key(package_name)
+            {
+                Logger::warn(format!("'{package_name}' is not installed"));
+            }
+        }
+
+        let mut pruned_count = 0;
+
+        for (package_name, _) in linked_packages {
+            if required_packages.contains(&package_name) {
+                continue;
+            }
+
+            NodeModules::unlink(&package_name)?;
+
+            match self.package_names.contains(&package_name) {
+                true => Logger::info(format!("Removed '{package_name}'")),
+                false => pruned_count += 1,
+            }
+        }
+
+        if pruned_count > 0 {
+            Logger::info(format!(
+                "Removed {pruned_count} dependencies that are no longer needed"
+            ));
+        }
+
+        NodeModules::write_lock(&[])
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::{
+    cache::Cache,
+    errors::{CommandError, ParseError},
+    installer::Installer,
+    logger::Logger,
+    node_modules::NodeModules,
+    package_json::{DependencyType, PackageJson, PackageJsonEditor},
+    versions::Versions,
+};
+
+use super::{
+    command_handler::{CommandArgs, CommandHandler},
+    install::{InstallHandler, InstallOptions},
+};
+
+struct PackageUpdate {
+    package_name: String,
+    dependency_type: DependencyType,
+    current_version: Option<String>,
+    new_version: String,
+    is_exact: bool,
+    // `npm:package@` if the dependency is an alias, so the new version points at the same package.
+    alias_prefix: String,
+}
+
+/// Moves direct dependencies to the newest version their range (or the registry with `--latest`) allows.
+#[derive(Default)]
+pub struct UpdateHandler {
+    // If this is empty, every dependency in the package.json will be updated.
+    package_names: Vec<String>,
+    latest: bool,
+    install_options: InstallOptions,
+}
+
+impl UpdateHandler {
+    /// Works out which version each dependency should be on, only dependencies that need to change are returned.
+    async fn find_updates(
+        &self,
+        client: reqwest::Client,
+    ) -> Result<Vec<PackageUpdate>, CommandError> {
+        let mut dependencies = PackageJson::read()?.typed_dependencies();
+        let linked_packages = NodeModules::linked_packages()?;
+
+        if !self.package_names.is_empty() {
+            if let Some(package_name) = self
+                .package_names
+                .iter()
+                .find(|package_name| !dependencies.contains_key(*package_name))
+            {
+                return Err(CommandError::NotADependency(package_name.to_string()));
+            }
+
+            dependencies.retain(|package_name, _| self.package_names.contains(package_name));
+        }
+
+        let mut updates = Vec::new();
+
+        for (package_name, (dependency_type, raw_version)) in dependencies {
+            let version_spec = Versions::parse_dependency_version(&raw_version).map_err(|err| {
+                CommandError::InvalidDependencyVersion(package_name.to_string(), err)
+            })?;
+
+            // Git and local packages follow their repository or folder rather than the registry
+            if !version_spec.is_registry() {
+                continue;
+            }
+
+            let (real_name, real_version_spec) = version_spec.real_package(&package_name);
+
+            let new_version = match self.latest {
+                true => Installer::resolve_latest_version(client.clone(), real_name).await?,
+                false => {
+                    Installer::resolve_newest_version(client.clone(), real_name, real_version_spec)
+                        .await?
+                }
+            };
+
+            let current_version = linked_packages.get(&package_name).map(|cached_package| {
+                Versions::parse_raw_package_details(Versions::real_package(cached_package)).1
+            });
+
+            if current_version.as_ref() == Some(&new_version) {
+                continue;
+            }
+
+            updates.push(PackageUpdate {
+                package_name,
+                dependency_type,
+                current_version,
+                new_version,
+                is_exact: version_spec.is_exact(),
+                alias_prefix: version_spec.alias_prefix(),
+            });
+        }
+
+        Ok(updates)
+    }
+
+    /// Unlinks the old version of each updated package along with its dependencies, so the new version's dependencies are linked instead.
+    fn unlink_old_versions(updates: &[PackageUpdate]) -> Result<(), CommandError> {
+        let mut linked_packages = NodeModules::linked_packages()?;
+
+        for update in updates {
+            let Some(cached_package) = linked_packages.get(&update.package_name) else {
+                continue;
+            };
+
+            for dependency in Cache::dependency_tree(cached_package.to_string())? {
+                let (dependency_name, _) = Versions::parse_raw_package_details(dependency);
+
+                if linked_packages.remove(&dependency_name).is_some() {
+                    NodeModules::unlink(&dependency_name)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Links every dependency that was not updated again, in case it or one of its dependencies was unlinked with an old version.
+    /// Top level packages that aren't in the package.json (such as installed peer dependencies) are linked again too.
+    /// `previously_linked` should be the linked packages from before anything was unlinked.
+    fn relink_unchanged(
+        updates: &[PackageUpdate],
+        previously_linked: HashMap<String, String>,
+    ) -> Result<(), CommandError> {
+        let mut root_packages = NodeModules::top_level_packages(&previously_linked)?;
+        root_packages.extend(PackageJson::read()?.all_dependencies().into_keys());
+
+        for (package_name, cached_package) in previously_linked {
+            let is_updated = updates
+                .iter()
+                .any(|update| update.package_name == package_name);
+
+            if root_packages.contains(&package_name) && !is_updated {
+                Cache::load_cached_version(cached_package);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CommandHandler for UpdateHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        for arg in args {
+            match arg.as_str() {
+                "--latest" => self.latest = true,
+                "--strict-peer-dependencies" => {
+                    self.install_options.strict_peer_dependencies = true
+                }
+                _ if arg.starts_with('-') => return Err(ParseError::UnknownFlag(arg)),
+                _ => self.package_names.push(arg),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let client = reqwest::Client::new();
+        let updates = self.find_updates(client).await?;
+
+        if updates.is_empty() {
+            Logger::info("Everything is up to date");
+            return Ok(());
+        }
+
+        let previously_linked = NodeModules::linked_packages()?;
+        Self::unlink_old_versions(&updates)?;
+
+        let packages = updates
+            .iter()
+            .map(|update| {
+                let exact_version = format!("{}={}", update.alias_prefix, update.new_version);
+                Ok((
+                    update.package_name.to_string(),
+                    Versions::parse_dependency_version(&exact_version)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, ParseError>>()
+            .map_err(CommandError::InvalidResolvedVersion)?;
+
+        let results = InstallHandler::install_packages(&packages, &self.install_options).await?;
+        let failed_count = results.iter().filter(|result| result.is_err()).count();
+
+        // Packages that failed to update are relinked to their old version along with everything else that didn't change
+        let updates = updates
+            .into_iter()
+            .zip(results)
+            .filter_map(|(update, result)| result.ok().map(|_| update))
+            .collect::<Vec<_>>();
+
+        Self::relink_unchanged(&updates, previously_linked)?;
+
+        if self.latest {
+            let mut package_json = PackageJsonEditor::open()?;
+
+            for update in updates.iter() {
+                let saved_version = match update.is_exact {
+                    true => format!("{}{}", update.alias_prefix, update.new_version),
+                    false => format!("{}^{}", update.alias_prefix, update.new_version),
+                };
+
+                package_json.set_dependency(
+                    update.dependency_type,
+                    &update.package_name,
+                    &saved_version,
+                );
+            }
+
+            package_json.save()?;
+        }
+
+        for update in updates {
+            match update.current_version {
+                Some(current_version) => Logger::info(format!(
+                    "Updated '{}' from {} to {}",
+                    update.package_name, current_version, update.new_version
+                )),
+                None => Logger::info(format!(
+                    "Installed '{}@{}'",
+                    update.package_name, update.new_version
+                )),
+            }
+        }
+
+        match failed_count {
+            0 => Ok(()),
+            _ => Err(CommandError::PackagesFailed(failed_count)),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    cache::Cache,
+    config::Config,
+    errors::{CommandError, ParseError},
+    node_modules::NodeModules,
+    package_json::{PackageJson, PACKAGE_JSON_PATH},
+    types::PackageLock,
+    versions::{VersionSpec, Versions},
+};
+
+use super::command_handler::{CommandArgs, CommandHandler};
+
+/// A single step in a dependency path, the range is None if it wasn't recorded.
+#[derive(Clone)]
+struct PathStep {
+    package: String,
+    range: Option<String>,
+}
+
+/// Each version of the target package mapped to the dependency type and path of everything that leads to it.
+type FoundPaths = Vec<(String, Vec<(String, Vec<PathStep>)>)>;
+
+/// Explains why a package is installed by printing every dependency path from the direct dependencies down to it.
+#[derive(Default)]
+pub struct WhyHandler {
+    // What the package was asked for with (package@range), used when nothing matches it.
+    package_details: String,
+    package_name: String,
+    version_spec: VersionSpec,
+}
+
+impl WhyHandler {
+    /// The direct dependencies of the project mapped to what they are linked to and the range they were declared with.
+    fn root_packages() -> Result<Vec<(String, PathStep)>, CommandError> {
+        let linked_packages = NodeModules::linked_packages()?;
+
+        let mut root_packages = match Path::new(PACKAGE_JSON_PATH).exists() {
+            true => PackageJson::read()?
+                .typed_dependencies()
+                .into_iter()
+                .filter_map(|(package_name, (dependency_type, range))| {
+                    let package = linked_packages.get(&package_name)?.to_string();
+                    let root = PathStep {
+                        package,
+                        range: Some(range),
+                    };
+
+                    Some((dependency_type.field_name().to_string(), root))
+                })
+                .collect::<Vec<_>>(),
+            false => NodeModules::top_level_packages(&linked_packages)?
+                .into_iter()
+                .filter_map(|package_name| {
+                    let package = linked_packages.get(&package_name)?.to_string();
+                    Some((
+                        String::from("node_modules"),
+                        PathStep {
+                            package,
+                            range: None,
+                        },
+                    ))
+                })
+                .collect::<Vec<_>>(),
+        };
+
+        root_packages.sort_by(|a, b| a.1.package.cmp(&b.1.package));
+        Ok(root_packages)
+    }
+
+    /// Aliased packages are found by both their alias and the package they point to.
+    fn is_target(&self, package: &str) -> bool {
+        let (alias, _) = Versions::parse_raw_package_details(package.to_string());
+        let (package_name, version) =
+            Versions::parse_raw_package_details(Versions::real_package(package));
+
+        (package_name == self.package_name || alias == self.package_name)
+            && self.version_spec.matches(&version)
+    }
+
+    /// Reads the package locks of everything below the root packages, then finds every package that leads to the target package.
+    /// Paths are only walked through these, so shared dependencies that can't reach the target aren't walked again for every path into them.
+    fn reaching_packages(
+        &self,
+        lockfiles: &mut HashMap<String, PackageLock>,
+        root_packages: &[(String, PathStep)],
+    ) -> Result<HashSet<String>, CommandError> {
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+        let mut reaching_packages = HashSet::new();
+        let mut pending = root_packages
+            .iter()
+            .map(|(_, root)| root.package.to_string())
+            .collect::<Vec<_>>();
+
+        while let Some(package) = pending.pop() {
+            if self.is_target(&package) {
+                reaching_packages.insert(package);
+                continue;
+            }
+
+            if lockfiles.contains_key(&package) {
+                continue;
+            }
+
+            let lockfile = Cache::read_lockfile(&package)?;
+            for dependency in lockfile.dependencies.iter() {
+                dependents
+                    .entry(dependency.to_string())
+                    .or_default()
+                    .push(package.to_string());
+                pending.push(dependency.to_string());
+            }
+
+            lockfiles.insert(package, lockfile);
+        }
+
+        let mut pending = reaching_packages.iter().cloned().collect::<Vec<_>>();
+
+        while let Some(package) = pending.pop() {
+            for dependent in dependents.remove(&package).unwrap_or_default() {
+                if reaching_packages.insert(dependent.to_string()) {
+                    pending.push(dependent);
+                }
+            }
+        }
+
+        Ok(reaching_packages)
+    }
+
+    /// Walks the package locks depth first, every path that reaches the target package is added to `found_paths`
+    /// along with the version of the target package it reached.
+    /// Only `reaching_packages` are walked into, their locks have already been read into `lockfiles`.
+    fn find_paths(
+        &self,
+        lockfiles: &HashMap<String, PackageLock>,
+        reaching_packages: &HashSet<String>,
+        path: &mut Vec<PathStep>,
+        found_paths: &mut Vec<(String, Vec<PathStep>)>,
+    ) {
+        let package = path.last().unwrap().package.to_string();
+
+        if self.is_target(&package) {
+            found_paths.push((package, path.clone()));
+            return;
+        }
+
+        let lockfile = &lockfiles[&package];
+        let mut dependencies = lockfile
+            .dependencies
+            .iter()
+            .map(|dependency| {
+                let (dependency_name, _) =
+                    Versions::parse_raw_package_details(dependency.to_string());
+                PathStep {
+                    package: dependency.to_string(),
+                    range: lockfile.ranges.get(&dependency_name).cloned(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        dependencies.sort_by(|a, b| a.package.cmp(&b.package));
+
+        for dependency in dependencies {
+            // Packages that can't reach the target are skipped, so are cycles as they can't lead anywhere new
+            if !reaching_packages.contains(&dependency.package)
+                || path.iter().any(|step| step.package == dependency.package)
+            {
+                continue;
+            }
+
+            path.push(dependency);
+            self.find_paths(lockfiles, reaching_packages, path, found_paths);
+            path.pop();
+        }
+    }
+
+    /// Steps are formatted as package@range (version), or package@version if the range is unknown.
+    fn format_step(step: &PathStep) -> String {
+        let (package_name, _) = Versions::parse_raw_package_details(step.package.to_string());
+        let (_, version) =
+            Versions::parse_raw_package_details(Versions::real_package(&step.package));
+
+        match &step.range {
+            Some(range) => format!("{package_name}@{range} ({version})"),
+            None => step.package.to_string(),
+        }
+    }
+
+    fn print_paths(found_paths: FoundPaths) {
+        for (package, paths) in found_paths {
+            println!("{package}");
+
+            for (dependency_type, path) in paths {
+                let formatted_path = path
+                    .iter()
+                    .map(Self::format_step)
+                    .collect::<Vec<_>>()
+                    .join(" > ");
+
+                println!("  {dependency_type}: {formatted_path}");
+            }
+        }
+    }
+
+    /// Prints each version of the package mapped to the paths that lead to it.
+    fn print_json(found_paths: FoundPaths) -> Result<(), CommandError> {
+        let mut json = Map::new();
+
+        for (package, paths) in found_paths {
+            let paths = paths
+                .into_iter()
+                .map(|(dependency_type, path)| {
+                    let path = path
+                        .into_iter()
+                        .map(|step| {
+                            let (name, _) =
+                                Versions::parse_raw_package_details(step.package.to_string());
+                            let (_, version) = Versions::parse_raw_package_details(
+                                Versions::real_package(&step.package),
+                            );
+                            json!({ "name": name, "version": version, "range": step.range })
+                        })
+                        .collect::<Vec<_>>();
+
+                    json!({ "type": dependency_type, "path": path })
+                })
+                .collect::<Vec<_>>();
+
+            json.insert(package, Value::Array(paths));
+        }
+
+        let json = serde_json::to_string_pretty(&Value::Object(json))
+            .map_err(CommandError::FailedToSerializeOutput)?;
+
+        println!("{json}");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CommandHandler for WhyHandler {
+    fn parse(&mut self, args: &mut CommandArgs) -> Result<(), ParseError> {
+        let package_details = args
+            .next()
+            .ok_or(ParseError::MissingArgument(String::from("package name")))?;
+
+        if let Some(arg) = args.next() {
+            return Err(ParseError::UnknownFlag(arg));
+        }
+
+        (self.package_name, self.version_spec) =
+            Versions::parse_semantic_package_details(package_details.to_string())?;
+        self.package_details = package_details;
+
+        Ok(())
+    }
+
+    async fn execute(&self) -> Result<(), CommandError> {
+        let root_packages = Self::root_packages()?;
+        let mut lockfiles = HashMap::new();
+        let reaching_packages = self.reaching_packages(&mut lockfiles, &root_packages)?;
+        let mut found_paths: HashMap<String, Vec<(String, Vec<PathStep>)>> = HashMap::new();
+
+        for (dependency_type, root) in root_packages {
+            if !reaching_packages.contains(&root.package) {
+                continue;
+            }
+
+            let mut paths = Vec::new();
+            self.find_paths(&lockfiles, &reaching_packages, &mut vec![root], &mut paths);
+
+            for (package, path) in paths {
+                found_paths
+                    .entry(package)
+                    .or_default()
+                    .push((dependency_type.to_string(), path));
+            }
+        }
+
+        if found_paths.is_empty() {
+            return Err(CommandError::PackageNotInstalled(
+                self.package_details.to_string(),
+            ));
+        }
+
+        let mut found_paths = found_paths.into_iter().collect::<Vec<_>>();
+        found_paths.sort_by(|a, b| a.0.cmp(&b.0));
+
+        match Config::get().json {
+            true => Self::print_json(found_paths)?,
+            false => Self::print_paths(found_paths),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+use crate::{http::DEFAULT_REGISTRY_URL, logger::LogLevel};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Options set by the global flags, these apply to every command.
+pub struct Config {
+    pub registry: String,
+    pub log_level: LogLevel,
+    pub json: bool,
+    // The folder the package and git caches are kept in, the platform's cache folder if it isn't set.
+    pub cache: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            registry: DEFAULT_REGISTRY_URL.to_string(),
+            log_level: LogLevel::default(),
+            json: false,
+            cache: None,
+        }
+    }
+}
+
+impl Config {
+    /// Should only be called once, before any command is executed.
+    pub fn set(config: Config) {
+        if CONFIG.set(config).is_err() {
+            panic!("Config has already been set");
+        }
+    }
+
+    pub fn get() -> &'static Config {
+        CONFIG.get_or_init(Config::default)
+    }
+
+    pub fn cache_directory(&self) -> PathBuf {
+        self.cache
+            .clone()
+            .unwrap_or_else(|| dirs::cache_dir().expect("Failed to find cache directory"))
+    }
+}
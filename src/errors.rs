@@ -1,14 +1,31 @@
 use std::io::Error;
 use thiserror::Error;
 
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(", did you mean '{suggestion}'?"),
+        None => String::new(),
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ParseError {
-    #[error("command '{0}' not found")]
-    CommandNotFound(String),
+    #[error("command '{0}' not found{}", did_you_mean(.1))]
+    CommandNotFound(String, Option<String>),
     #[error("missing argument: '{0}'")]
     MissingArgument(String),
-    #[error("invalid version notation ({0})")]
-    InvalidVersionNotation(semver::Error),
+    #[error("unknown flag '{0}'")]
+    UnknownFlag(String),
+    #[error("unknown flag '{0}'{}, see '{1} --help'", did_you_mean(.2))]
+    UnknownCommandFlag(String, String, Option<String>),
+    #[error("flag '{0}' does not take a value")]
+    UnexpectedFlagValue(String),
+    #[error("invalid value for '{0}': '{1}'")]
+    InvalidArgument(String, String),
+    #[error("invalid version range '{0}' ({1})")]
+    InvalidVersionRange(String, String),
+    #[error("invalid alias '{0}' ({1})")]
+    InvalidAlias(String, String),
 }
 
 #[derive(Error, Debug)]
@@ -21,10 +38,16 @@ pub enum CommandError {
     FailedResponseText(reqwest::Error),
     #[error("failed to get http response bytes ({0})")]
     FailedResponseBytes(reqwest::Error),
+    #[error("failed to download '{0}' ({1})")]
+    DownloadFailed(String, String),
     #[error("the package version you provided was invalid or does not exist")]
     InvalidVersion,
+    #[error("no version is tagged '{0}'")]
+    TagNotFound(String),
     #[error("failed to extract tar file ({0})")]
     ExtractionFailed(Error),
+    #[error("could not get the working directory ({0})")]
+    NoWorkingDirectory(Error),
     #[error("could not find cache directory ({0})")]
     NoCacheDirectory(Error),
     #[error("failed to get directory entry ({0})")]
@@ -33,6 +56,66 @@ pub enum CommandError {
     FailedToCreateFile(Error),
     #[error("failed to write file ({0})")]
     FailedToWriteFile(Error),
+    #[error("failed to remove file ({0})")]
+    FailedToRemoveFile(Error),
     #[error("failed to serialize package lock ({0})")]
     FailedToSerializePackageLock(serde_json::Error),
+    #[error("failed to read package lock ({0})")]
+    FailedToReadPackageLock(Error),
+    #[error("failed to parse package lock ({0})")]
+    FailedToParsePackageLock(serde_json::Error),
+    #[error("failed to read package.json ({0})")]
+    FailedToReadPackageJson(Error),
+    #[error("failed to parse package.json ({0})")]
+    FailedToParsePackageJson(serde_json::Error),
+    #[error("failed to serialize package.json ({0})")]
+    FailedToSerializePackageJson(serde_json::Error),
+    #[error("invalid version for dependency '{0}' ({1})")]
+    InvalidDependencyVersion(String, ParseError),
+    #[error("failed to serialize output ({0})")]
+    FailedToSerializeOutput(serde_json::Error),
+    #[error("failed to install dependency '{0}' ({1})")]
+    DependencyFailed(String, String),
+    #[error("failed to install '{0}' ({1})")]
+    InstallFailed(String, String),
+    #[error("{0} of the packages failed to install")]
+    PackagesFailed(usize),
+    #[error("{0} of the dependencies could not be checked")]
+    OutdatedCheckFailed(usize),
+    #[error("{0} of the dependencies are outdated")]
+    OutdatedDependencies(usize),
+    #[error("'{0}' is not installed")]
+    PackageNotInstalled(String),
+    #[error("'{0}' is not a dependency in package.json")]
+    NotADependency(String),
+    #[error("resolved an invalid version ({0})")]
+    InvalidResolvedVersion(ParseError),
+    #[error("invalid package name '{0}' ({1})")]
+    InvalidPackageName(String, String),
+    #[error("failed to read input ({0})")]
+    FailedToReadInput(Error),
+    #[error("'git {0}' failed ({1})")]
+    GitFailed(String, String),
+    #[error("could not find '{0}' in '{1}'")]
+    GitRefNotFound(String, String),
+    #[error("the package in '{0}' has no name in its package.json")]
+    MissingPackageName(String),
+    #[error("the prepare script of '{0}' failed ({1})")]
+    PrepareFailed(String, String),
+    #[error("failed to pack '{0}' ({1})")]
+    FailedToPack(String, Error),
+    #[error("failed to install the dependencies of '{0}' ({1})")]
+    NestedInstallFailed(String, String),
+    #[error("could not find a package at '{0}' ({1})")]
+    LocalPackageNotFound(String, Error),
+    #[error("'{0}' can only be used by the project or another local package")]
+    UnexpectedLocalDependency(String),
+    #[error("'{0}' is not a valid package tarball ({1})")]
+    InvalidTarball(String, String),
+    #[error("'{0}' has a peer dependency on '{1}', but '{2}' is installed")]
+    PeerDependencyConflict(String, String, String),
+    #[error("'{0}' does not support this platform ({1})")]
+    UnsupportedPlatform(String, String),
+    #[error("invalid override '{0}' ({1})")]
+    InvalidOverride(String, String),
 }
@@ -0,0 +1,518 @@
+use std::{env, fmt::Display, fs, path::Path, process, str::FromStr, sync::Mutex};
+
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use semver::Version;
+
+use crate::{
+    cache::PACKAGE_CACHE_FOLDER,
+    config::Config,
+    errors::CommandError,
+    installer::Installer,
+    logger::Logger,
+    node_modules::NODE_MODULES_PATH,
+    package_json::PackageJson,
+    types::VersionData,
+    util,
+    versions::{Versions, GIT_BUILD_PREFIX},
+};
+
+lazy_static! {
+    pub static ref GIT_CACHE_DIRECTORY: String = format!(
+        "{}/node-git-cache",
+        Config::get()
+            .cache_directory()
+            .to_str()
+            .expect("Failed to convert cache directory to string")
+    );
+    // Git refuses to touch a repository another git process has locked, so the cached repositories are updated one at a time.
+    static ref REPOSITORY_LOCK: Mutex<()> = Mutex::new(());
+}
+
+// A committish starting with this is a version range, which resolves to the highest tag that satisfies it (#semver:^1.2).
+const SEMVER_PREFIX: &str = "semver:";
+
+/// A package that is fetched from a git repository instead of the registry.
+/// `committish` is the branch, tag, commit or `semver:<range>` after the `#`, the default branch is used without one.
+#[derive(Clone, Debug)]
+pub struct GitSpec {
+    pub url: String,
+    pub committish: Option<String>,
+    raw: String,
+}
+
+impl GitSpec {
+    /// Parses the git specifiers npm supports, None is returned if `raw_spec` isn't one.
+    /// github:user/repo (or just user/repo), gitlab:user/repo, bitbucket:user/repo and git+<protocol>:// urls, all with an optional #committish.
+    pub fn parse(raw_spec: &str) -> Option<Self> {
+        let (location, committish) = match raw_spec.split_once('#') {
+            Some((location, committish)) if !committish.is_empty() => {
+                (location, Some(committish.to_string()))
+            }
+            Some((location, _)) => (location, None),
+            None => (raw_spec, None),
+        };
+
+        let url = match location.split_once(':') {
+            Some(("github", path)) => Self::hosted_url("github.com", path)?,
+            Some(("gitlab", path)) => Self::hosted_url("gitlab.com", path)?,
+            Some(("bitbucket", path)) => Self::hosted_url("bitbucket.org", path)?,
+            Some(_) if location.starts_with("git://") => location.to_string(),
+            Some(_) => Self::ssh_url(location.strip_prefix("git+")?),
+            // Scoped package names also contain a `/`, but never start a version
+            None if !location.starts_with('@') => Self::hosted_url("github.com", location)?,
+            None => return None,
+        };
+
+        Some(Self {
+            url,
+            committish,
+            raw: raw_spec.to_string(),
+        })
+    }
+
+    fn hosted_url(host: &str, path: &str) -> Option<String> {
+        let (user, repository) = path.split_once('/')?;
+        let repository = repository.trim_end_matches(".git");
+
+        let is_valid = [user, repository].iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || matches!(char, '-' | '.' | '_'))
+        });
+
+        match is_valid {
+            true => Some(format!("https://{host}/{user}/{repository}.git")),
+            false => None,
+        }
+    }
+
+    /// npm allows ssh urls with a `:` before the path (git+ssh://git@github.com:user/repo.git),
+    /// git reads that as a port so it is turned into the scp-like syntax git expects (git@github.com:user/repo.git).
+    fn ssh_url(url: &str) -> String {
+        let Some(address) = url.strip_prefix("ssh://") else {
+            return url.to_string();
+        };
+
+        match address.split_once(':') {
+            Some((_, path)) if !path.starts_with(|char: char| char.is_ascii_digit()) => {
+                address.to_string()
+            }
+            _ => url.to_string(),
+        }
+    }
+
+    /// The same repository pinned to a single commit.
+    pub fn at_commit(&self, commit: &str) -> Self {
+        Self {
+            url: self.url.to_string(),
+            committish: Some(commit.to_string()),
+            raw: format!("git+{}#{commit}", self.url),
+        }
+    }
+}
+
+impl Display for GitSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+pub struct Git;
+impl Git {
+    /// Resolves the committish of a git package to a commit and reads the package.json at that commit.
+    /// The returned version data points at the commit, so packing its "tarball" always gives the same files.
+    pub fn version_data(git_spec: &GitSpec) -> Result<VersionData, CommandError> {
+        Self::version_data_in(&GIT_CACHE_DIRECTORY, git_spec)
+    }
+
+    /// Checks out a commit of a cached repository and packs it into a tarball, laid out the same as the registry's.
+    /// If the package has a prepare script its dependencies are installed and the script is run first, like npm does.
+    pub fn pack(git_spec: &GitSpec) -> Result<Bytes, CommandError> {
+        Self::pack_in(&GIT_CACHE_DIRECTORY, git_spec)
+    }
+
+    // The functions below take the folder repositories are cloned into, which is only something other than GIT_CACHE_DIRECTORY in tests.
+
+    fn version_data_in(
+        cache_directory: &str,
+        git_spec: &GitSpec,
+    ) -> Result<VersionData, CommandError> {
+        let commit = Self::resolve_commit(cache_directory, git_spec)?;
+        let repository = Self::repository_directory(cache_directory, &git_spec.url);
+
+        let package_json_raw = Self::run(
+            Some(&repository),
+            &["show", &format!("{commit}:package.json")],
+        )?;
+        let package_json = PackageJson::parse(&package_json_raw)?;
+
+        let name = package_json
+            .name
+            .clone()
+            .ok_or(CommandError::MissingPackageName(git_spec.url.to_string()))?;
+
+        // Build metadata is replaced rather than appended to, as a version can only have one
+        let version = package_json
+            .version
+            .clone()
+            .unwrap_or(String::from("0.0.0"));
+        let (version, _) = version.split_once('+').unwrap_or((&version, ""));
+
+        Ok(VersionData::from_package_json(
+            package_json,
+            name,
+            format!("{version}+{GIT_BUILD_PREFIX}{commit}"),
+            git_spec.at_commit(&commit).to_string(),
+        ))
+    }
+
+    /// Clones the repository into the git cache, or fetches it if it's already there, then resolves the committish to a commit.
+    /// Fetching is skipped if the committish is a full commit that is already cached, as it can't have changed.
+    fn resolve_commit(cache_directory: &str, git_spec: &GitSpec) -> Result<String, CommandError> {
+        let _lock = REPOSITORY_LOCK.lock().unwrap();
+
+        let repository = Self::repository_directory(cache_directory, &git_spec.url);
+        let committish = git_spec.committish.as_deref().unwrap_or("HEAD");
+        let commit_ref = format!("{committish}^{{commit}}");
+
+        let is_full_commit =
+            committish.len() == 40 && committish.chars().all(|char| char.is_ascii_hexdigit());
+
+        if is_full_commit && Path::new(&repository).exists() {
+            if let Ok(commit) =
+                Self::run(Some(&repository), &["rev-parse", "--verify", &commit_ref])
+            {
+                return Ok(commit.trim().to_string());
+            }
+        }
+
+        match Path::new(&repository).exists() {
+            true => Self::run(
+                Some(&repository),
+                &["fetch", "--quiet", "--prune", "origin"],
+            )?,
+            false => {
+                fs::create_dir_all(cache_directory).map_err(CommandError::FailedToCreateFile)?;
+
+                Self::run(
+                    None,
+                    &["clone", "--quiet", "--mirror", &git_spec.url, &repository],
+                )?
+            }
+        };
+
+        let ref_not_found =
+            || CommandError::GitRefNotFound(committish.to_string(), git_spec.url.to_string());
+
+        let commit_ref = match committish.strip_prefix(SEMVER_PREFIX) {
+            Some(raw_range) => {
+                let tag =
+                    Self::resolve_semver_tag(&repository, raw_range)?.ok_or_else(ref_not_found)?;
+                format!("{tag}^{{commit}}")
+            }
+            None => commit_ref,
+        };
+
+        match Self::run(Some(&repository), &["rev-parse", "--verify", &commit_ref]) {
+            Ok(commit) => Ok(commit.trim().to_string()),
+            Err(_) => Err(ref_not_found()),
+        }
+    }
+
+    /// Finds the highest tag that satisfies a `#semver:<range>` committish, tags may start with a `v` (v1.2.3).
+    fn resolve_semver_tag(
+        repository: &str,
+        raw_range: &str,
+    ) -> Result<Option<String>, CommandError> {
+        let version_range = Versions::parse_semantic_version(raw_range)
+            .map_err(|err| CommandError::InvalidDependencyVersion(raw_range.to_string(), err))?;
+
+        let tags = Self::run(Some(repository), &["tag", "--list"])?;
+
+        Ok(tags
+            .lines()
+            .filter_map(|tag| {
+                let version = Version::from_str(tag.strip_prefix('v').unwrap_or(tag)).ok()?;
+                version_range
+                    .matches(&version)
+                    .then(|| (version, tag.to_string()))
+            })
+            .max_by(|a, b| Versions::compare(&a.0, &b.0))
+            .map(|(_, tag)| tag))
+    }
+
+    fn pack_in(cache_directory: &str, git_spec: &GitSpec) -> Result<Bytes, CommandError> {
+        let commit = git_spec
+            .committish
+            .as_deref()
+            .expect("Git packages are only packed once they are pinned to a commit");
+
+        let repository = Self::repository_directory(cache_directory, &git_spec.url);
+        let checkout = format!("{repository}.{commit}");
+        let prepare_cache = format!("{checkout}.cache");
+
+        // A previous install might have been stopped half way through
+        for directory in [&checkout, &prepare_cache] {
+            if Path::new(directory).exists() {
+                fs::remove_dir_all(directory).map_err(CommandError::FailedToRemoveFile)?;
+            }
+        }
+
+        Self::run(
+            None,
+            &["clone", "--quiet", "--no-checkout", &repository, &checkout],
+        )?;
+        Self::run(Some(&checkout), &["checkout", "--quiet", commit])?;
+
+        let result = Self::prepare(git_spec, &checkout, &prepare_cache).and_then(|_| {
+            util::pack_directory(Path::new(&checkout))
+                .map_err(|err| CommandError::FailedToPack(git_spec.to_string(), err))
+        });
+        fs::remove_dir_all(&checkout).map_err(CommandError::FailedToRemoveFile)?;
+
+        if Path::new(&prepare_cache).exists() {
+            fs::remove_dir_all(&prepare_cache).map_err(CommandError::FailedToRemoveFile)?;
+        }
+
+        result
+    }
+
+    /// The dependencies the prepare script needs are installed with their own cache, `prepare_cache`, which is only needed until the package is packed.
+    /// Sharing the cache would have the nested install extracting into the same folders as the install that is packing this package.
+    fn prepare(
+        git_spec: &GitSpec,
+        checkout: &str,
+        prepare_cache: &str,
+    ) -> Result<(), CommandError> {
+        let package_json_raw = fs::read_to_string(format!("{checkout}/package.json"))
+            .map_err(CommandError::FailedToReadPackageJson)?;
+
+        let Some(script) = PackageJson::parse(&package_json_raw)?
+            .scripts
+            .and_then(|mut scripts| scripts.remove("prepare"))
+        else {
+            return Ok(());
+        };
+
+        Logger::info(format!("Running the prepare script of '{git_spec}'.."));
+
+        let prepare_failed = |err: String| CommandError::PrepareFailed(git_spec.to_string(), err);
+
+        fs::create_dir_all(format!("{prepare_cache}/{PACKAGE_CACHE_FOLDER}"))
+            .map_err(|err| prepare_failed(err.to_string()))?;
+
+        // Dev dependencies are installed too, as they are usually what the prepare script needs
+        Installer::install_in_directory(Path::new(checkout), Path::new(prepare_cache))
+            .map_err(|err| prepare_failed(err.to_string()))?;
+
+        let (shell, shell_flag) = match cfg!(windows) {
+            true => ("cmd", "/C"),
+            false => ("sh", "-c"),
+        };
+
+        let path = env::var("PATH").unwrap_or_default();
+        let separator = if cfg!(windows) { ';' } else { ':' };
+
+        let script_output = process::Command::new(shell)
+            .args([shell_flag, &script])
+            .current_dir(checkout)
+            .env(
+                "PATH",
+                format!("{checkout}/{NODE_MODULES_PATH}/.bin{separator}{path}"),
+            )
+            .output()
+            .map_err(|err| prepare_failed(err.to_string()))?;
+
+        match script_output.status.success() {
+            true => Ok(()),
+            false => Err(prepare_failed(
+                String::from_utf8_lossy(&script_output.stderr)
+                    .trim()
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Each repository is cloned into its own directory, named after its url with anything that isn't safe in a path replaced.
+    fn repository_directory(cache_directory: &str, url: &str) -> String {
+        let directory_name = url
+            .chars()
+            .map(
+                |char| match char.is_ascii_alphanumeric() || matches!(char, '-' | '.' | '_') {
+                    true => char,
+                    false => '+',
+                },
+            )
+            .collect::<String>();
+
+        format!("{cache_directory}/{directory_name}")
+    }
+
+    /// Runs git and returns what it printed, git's own error message is returned if it fails.
+    fn run(directory: Option<&str>, args: &[&str]) -> Result<String, CommandError> {
+        Logger::verbose(format!("git {}", args.join(" ")));
+
+        let mut command = process::Command::new("git");
+        if let Some(directory) = directory {
+            command.arg("-C").arg(directory);
+        }
+
+        let output = command
+            .args(args)
+            .output()
+            .map_err(|err| CommandError::GitFailed(args.join(" "), err.to_string()))?;
+
+        match output.status.success() {
+            true => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+            false => Err(CommandError::GitFailed(
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_git_specs() {
+        let cases = [
+            ("github:user/repo", "https://github.com/user/repo.git", None),
+            (
+                "user/repo#v1.0.0",
+                "https://github.com/user/repo.git",
+                Some("v1.0.0"),
+            ),
+            (
+                "gitlab:user/repo.git#main",
+                "https://gitlab.com/user/repo.git",
+                Some("main"),
+            ),
+            (
+                "bitbucket:user/repo",
+                "https://bitbucket.org/user/repo.git",
+                None,
+            ),
+            (
+                "git+https://example.com/repo.git#",
+                "https://example.com/repo.git",
+                None,
+            ),
+            (
+                "git+ssh://git@github.com:user/repo.git",
+                "git@github.com:user/repo.git",
+                None,
+            ),
+            (
+                "git+ssh://git@example.com:22/repo.git",
+                "ssh://git@example.com:22/repo.git",
+                None,
+            ),
+            (
+                "git://example.com/repo.git#abc123",
+                "git://example.com/repo.git",
+                Some("abc123"),
+            ),
+            (
+                "git+file:///tmp/repo.git#semver:^1.0",
+                "file:///tmp/repo.git",
+                Some("semver:^1.0"),
+            ),
+        ];
+
+        for (raw_spec, url, committish) in cases {
+            let git_spec = GitSpec::parse(raw_spec).unwrap_or_else(|| panic!("{raw_spec}"));
+
+            assert_eq!(git_spec.url, url, "{raw_spec}");
+            assert_eq!(git_spec.committish.as_deref(), committish, "{raw_spec}");
+            assert_eq!(git_spec.to_string(), raw_spec);
+        }
+
+        for raw_spec in ["1.2.3", "^1.0.0", "@scope/package", "github:user", "latest"] {
+            assert!(GitSpec::parse(raw_spec).is_none(), "{raw_spec}");
+        }
+    }
+
+    /// Commits a package.json with the version and tags it, returning the commit.
+    fn commit_version(work_tree: &str, version: &str) -> String {
+        fs::write(
+            format!("{work_tree}/package.json"),
+            format!(r#"{{ "name": "git-package", "version": "{version}" }}"#),
+        )
+        .unwrap();
+
+        let identity = ["-c", "user.name=click", "-c", "user.email=click@localhost"];
+        Git::run(Some(work_tree), &["add", "package.json"]).unwrap();
+        Git::run(
+            Some(work_tree),
+            &[&identity[..], &["commit", "--quiet", "-m", version]].concat(),
+        )
+        .unwrap();
+        Git::run(Some(work_tree), &["tag", &format!("v{version}")]).unwrap();
+
+        Git::run(Some(work_tree), &["rev-parse", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn resolves_and_packs_bare_repositories() {
+        let directory = env::temp_dir().join(format!("click-git-test-{}", process::id()));
+        let directory = directory.to_str().unwrap();
+        let work_tree = format!("{directory}/work");
+        let bare_repository = format!("{directory}/repo.git");
+        // Repositories are cloned in the test's own folder, so the real git cache is never touched
+        let cache_directory = format!("{directory}/git-cache");
+
+        fs::create_dir_all(&work_tree).unwrap();
+        Git::run(Some(&work_tree), &["init", "--quiet", "-b", "main"]).unwrap();
+        let first_commit = commit_version(&work_tree, "1.2.3");
+        let second_commit = commit_version(&work_tree, "2.0.0");
+        Git::run(
+            None,
+            &["clone", "--quiet", "--bare", &work_tree, &bare_repository],
+        )
+        .unwrap();
+
+        let cases = [
+            ("main", &second_commit, "2.0.0"),
+            ("v1.2.3", &first_commit, "1.2.3"),
+            ("semver:^1.0.0", &first_commit, "1.2.3"),
+            ("semver:*", &second_commit, "2.0.0"),
+        ];
+
+        for (committish, commit, version) in cases {
+            let git_spec =
+                GitSpec::parse(&format!("git+file://{bare_repository}#{committish}")).unwrap();
+
+            let version_data = Git::version_data_in(&cache_directory, &git_spec).unwrap();
+            assert_eq!(version_data.name, "git-package");
+            assert_eq!(
+                version_data.version,
+                format!("{version}+{GIT_BUILD_PREFIX}{commit}")
+            );
+
+            let tarball = Git::pack_in(&cache_directory, &git_spec.at_commit(commit)).unwrap();
+            let package_json = util::read_tarball_file(&tarball, "package.json")
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                PackageJson::parse(&package_json).unwrap().version.unwrap(),
+                version
+            );
+        }
+
+        let git_spec = GitSpec::parse(&format!("git+file://{bare_repository}#semver:^3")).unwrap();
+        assert!(matches!(
+            Git::resolve_commit(&cache_directory, &git_spec),
+            Err(CommandError::GitRefNotFound(_, _))
+        ));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
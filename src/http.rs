@@ -1,31 +1,45 @@
 use bytes::Bytes;
 
 use crate::{
+    config::Config,
     errors::CommandError::{self, *},
+    logger::Logger,
     types::{PackageData, VersionData},
 };
 
-pub const REGISTRY_URL: &str = "https://registry.npmjs.org";
+pub const DEFAULT_REGISTRY_URL: &str = "https://registry.npmjs.org";
 
 pub struct HTTPRequest;
 impl HTTPRequest {
     /// Download a file from any specified URL.
+    /// An error status is returned as a failed download, otherwise the error page would be read as the file.
     pub async fn get_bytes(client: reqwest::Client, url: String) -> Result<Bytes, CommandError> {
-        client
-            .get(url)
+        Logger::verbose(format!("GET {url}"));
+
+        let response = client
+            .get(&url)
             .send()
             .await
-            .map_err(CommandError::HTTPFailed)?
-            .bytes()
-            .await
-            .map_err(CommandError::FailedResponseBytes)
+            .map_err(CommandError::HTTPFailed)?;
+
+        let status = response.status();
+        match status.is_success() {
+            true => response
+                .bytes()
+                .await
+                .map_err(CommandError::FailedResponseBytes),
+            false => Err(DownloadFailed(url, status.to_string())),
+        }
     }
 
     /// Make a request to the NPM registry.
     /// This includes the recommended header to shorten the response size.
     async fn registry(client: reqwest::Client, route: String) -> Result<String, CommandError> {
+        let url = format!("{}{route}", Config::get().registry);
+        Logger::verbose(format!("GET {url}"));
+
         client
-            .get(format!("{REGISTRY_URL}{route}"))
+            .get(url)
             .header(
                 "Accept",
                 "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*",
@@ -38,13 +52,19 @@ impl HTTPRequest {
             .map_err(FailedResponseText)
     }
 
+    /// Scoped packages have their `/` encoded as the registry expects (@types%2fnode).
+    fn encode_package_name(package_name: &str) -> String {
+        package_name.replace('/', "%2f")
+    }
+
     /// This makes a request for a specific version of a package.
     /// This method should always be preferred where possible as its response size is significantly smaller than full package data.
     pub async fn version_data(
         client: reqwest::Client,
-        package_name: &String,
+        package_name: &str,
         version: &String,
     ) -> Result<VersionData, CommandError> {
+        let package_name = Self::encode_package_name(package_name);
         let response_raw = Self::registry(client, format!("/{package_name}/{version}")).await?;
         serde_json::from_str::<VersionData>(&response_raw).map_err(ParsingFailed)
     }
@@ -53,8 +73,9 @@ impl HTTPRequest {
     /// This method should be avoided where possible as its response size is much larger than just requesting version data.
     pub async fn package_data(
         client: reqwest::Client,
-        package_name: &String,
+        package_name: &str,
     ) -> Result<PackageData, CommandError> {
+        let package_name = Self::encode_package_name(package_name);
         let response_raw = Self::registry(client, format!("/{package_name}")).await?;
         serde_json::from_str::<PackageData>(&response_raw).map_err(ParsingFailed)
     }
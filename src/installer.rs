@@ -1,23 +1,34 @@
 use bytes::Bytes;
-use semver::Comparator;
+use semver::Version;
 use std::fs::{self};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    env, process,
     sync::{mpsc::Sender, Arc, Mutex},
 };
 
-use crate::util::TaskAllocator;
+use crate::util::{self, TaskAllocator};
 use crate::{
-    cache::{Cache, CACHE_DIRECTORY},
+    cache::Cache,
+    config::Config,
     errors::CommandError::{self},
+    git::{Git, GitSpec},
     http::HTTPRequest,
+    local::{Local, FILE_PROTOCOL, LINK_PROTOCOL},
+    logger::Logger,
+    overrides::Overrides,
+    platform::Platform,
+    remote::Remote,
     types::{DependencyMap, PackageLock, VersionData},
-    versions::{Versions, LATEST},
+    versions::{VersionSpec, Versions, LATEST},
 };
 
 pub type DependencyMapMutex = Arc<Mutex<DependencyMap>>;
-pub type PackageBytes = (String, Bytes); // Package destination, package bytes
+pub type FailureMapMutex = Arc<Mutex<HashMap<String, String>>>; // Stringified package, error message
+pub type PackageBytes = (String, Bytes); // Stringified package, package bytes
+pub type LinkedDirectoriesMutex = Arc<Mutex<Vec<(String, PathBuf)>>>; // Stringified package, folder of the linked package
 
 pub struct PackageInfo {
     pub version_data: VersionData,
@@ -30,6 +41,10 @@ pub struct InstallContext {
     pub client: reqwest::Client,
     pub bytes_sender: Sender<PackageBytes>,
     pub dependency_map_mux: DependencyMapMutex,
+    pub failure_map_mux: FailureMapMutex,
+    pub overrides: Arc<Overrides>,
+    // Linked packages whose dependencies still have to be installed, which is done once the install's lockfiles are written.
+    pub linked_directories_mux: LinkedDirectoriesMutex,
 }
 
 pub struct Installer;
@@ -37,17 +52,22 @@ impl Installer {
     /// Gets the version data taking in the full version rather than resolving it on its own.
     pub async fn get_version_data(
         client: reqwest::Client,
-        package_name: &String,
+        package_name: &str,
         full_version: Option<&String>,
-        semantic_version: Option<&Comparator>,
+        version_spec: &VersionSpec,
     ) -> Result<VersionData, CommandError> {
         if let Some(version) = full_version {
-            return HTTPRequest::version_data(client.clone(), package_name, version).await;
+            let version_data =
+                HTTPRequest::version_data(client.clone(), package_name, version).await?;
+
+            // `latest` is only a guess for ranges like >=1.2.0, if it's outside of the range every version has to be checked
+            if version_spec.matches(&version_data.version) {
+                return Ok(version_data);
+            }
         }
 
         let mut package_data = HTTPRequest::package_data(client.clone(), package_name).await?;
-        let package_version =
-            Versions::resolve_partial_version(semantic_version, &package_data.versions)?;
+        let package_version = Versions::resolve_partial_version(version_spec, &package_data)?;
 
         Ok(package_data
             .versions
@@ -55,8 +75,87 @@ impl Installer {
             .expect("Failed to find resolved package version in package data"))
     }
 
-    // NOTE(conaticus): To save storage space, it might be an idea to check if the semantic version matches,
-    // rather than installing an whole new version, however this is an uncommon case due to how we handle version resolution so it's not a big deal.
+    /// Resolves a git package to a commit and reads its version data from the repository.
+    pub async fn get_git_version_data(git_spec: &GitSpec) -> Result<VersionData, CommandError> {
+        let git_spec = git_spec.clone();
+
+        tokio::task::spawn_blocking(move || Git::version_data(&git_spec))
+            .await
+            .expect("Failed to join git task")
+    }
+
+    /// Downloads the tarball of a package, git and `file:` packages are packed from the cached repository or their folder instead.
+    async fn get_tarball(client: reqwest::Client, tarball: String) -> Result<Bytes, CommandError> {
+        if let Some(directory) = tarball.strip_prefix(FILE_PROTOCOL) {
+            let directory = directory.to_string();
+
+            return tokio::task::spawn_blocking(move || {
+                util::pack_directory(Path::new(&directory))
+                    .map_err(|err| CommandError::FailedToPack(directory, err))
+            })
+            .await
+            .expect("Failed to join pack task");
+        }
+
+        match GitSpec::parse(&tarball) {
+            Some(git_spec) => tokio::task::spawn_blocking(move || Git::pack(&git_spec))
+                .await
+                .expect("Failed to join git task"),
+            None => HTTPRequest::get_bytes(client, tarball).await,
+        }
+    }
+
+    /// Installs the dependencies of the package.json in another folder, dev dependencies included.
+    /// This is done by another click process, as the node modules folder is always the one in the working directory.
+    /// `cache_directory` is the cache it uses, only one process can be installing into a cache at a time.
+    pub fn install_in_directory(
+        directory: &Path,
+        cache_directory: &Path,
+    ) -> Result<(), CommandError> {
+        let install_failed = |reason: String| {
+            CommandError::NestedInstallFailed(directory.display().to_string(), reason)
+        };
+
+        let executable = env::current_exe().map_err(|err| install_failed(err.to_string()))?;
+
+        let status = process::Command::new(executable)
+            .arg("--cwd")
+            .arg(directory)
+            .args(["--registry", &Config::get().registry])
+            .arg("--cache")
+            .arg(cache_directory)
+            .args(["--loglevel", "error", "install"])
+            .status()
+            .map_err(|err| install_failed(err.to_string()))?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(install_failed(format!("click exited with {status}"))),
+        }
+    }
+
+    /// Gets the version the registry has tagged as latest.
+    pub async fn resolve_latest_version(
+        client: reqwest::Client,
+        package_name: &str,
+    ) -> Result<String, CommandError> {
+        Ok(
+            HTTPRequest::version_data(client, package_name, &LATEST.to_string())
+                .await?
+                .version,
+        )
+    }
+
+    /// Gets the newest version that satisfies `version_spec`, unlike get_version_data() this never takes the shortcut of using the minimum version.
+    pub async fn resolve_newest_version(
+        client: reqwest::Client,
+        package_name: &str,
+        version_spec: &VersionSpec,
+    ) -> Result<String, CommandError> {
+        let package_data = HTTPRequest::package_data(client, package_name).await?;
+        Versions::resolve_partial_version(version_spec, &package_data)
+    }
+
     /// Returns true if a given dependency's version has been/will be installed to avoid unneccesary duplicate installs
     /// If the dependency is not in the hashmap, it will be added to the hashmap for further checks.
     fn already_resolved(context: &InstallContext, package_info: &PackageInfo) -> bool {
@@ -71,125 +170,485 @@ impl Installer {
         match installed_version {
             Some(_) => true,
             None => {
-                dependency_map.insert(
-                    stringified_version,
-                    PackageLock::new(package_info.is_latest),
-                );
+                let version_data = &package_info.version_data;
+
+                let mut package_lock = PackageLock::new(package_info.is_latest);
+                package_lock.peer_dependencies =
+                    version_data.peer_dependencies.clone().unwrap_or_default();
+                package_lock.optional_peers = version_data.optional_peers();
+                package_lock.optional_dependencies = version_data
+                    .optional_dependencies
+                    .iter()
+                    .flatten()
+                    .map(|(package_name, _)| package_name.to_string())
+                    .collect();
+
+                dependency_map.insert(stringified_version, package_lock);
                 false
             }
         }
     }
 
-    /// Append a version to a specific parent version, this hashmap will be used to generate package lock files.
+    /// Finds the newest version of a package that has already been resolved during this install and satisfies the range,
+    /// so `^1.2.0` and `^1.4.0` share `1.5.3` rather than each installing their own version.
+    /// Only ranges are checked, tags have to be looked up in the registry to know what they point to.
+    fn find_resolved_version(
+        context: &InstallContext,
+        package_name: &str,
+        version_spec: &VersionSpec,
+    ) -> Option<String> {
+        let VersionSpec::Range(semantic_version) = version_spec else {
+            return None;
+        };
+
+        let dependency_map = context.dependency_map_mux.lock().unwrap();
+        let mut newest_version: Option<(Version, String)> = None;
+
+        for package in dependency_map.keys() {
+            let (name, version_str) = Versions::parse_raw_package_details(package.to_string());
+            if name != package_name || !Versions::is_registry_version(&version_str) {
+                continue;
+            }
+
+            let Ok(version) = Version::from_str(&version_str) else {
+                continue;
+            };
+
+            if !semantic_version.matches(&version) {
+                continue;
+            }
+
+            if newest_version.as_ref().is_none_or(|(newest_version, _)| {
+                Versions::compare(&version, newest_version).is_gt()
+            }) {
+                newest_version = Some((version, version_str));
+            }
+        }
+
+        newest_version.map(|(_, version_str)| version_str)
+    }
+
+    /// Append a version to the dependencies of its parent version, this hashmap will be used to generate package lock files.
+    /// `range` is the version range the parent requested, it is recorded so we can explain why a version was installed.
     fn append_version(
-        parents_mux: Arc<Mutex<Vec<String>>>,
+        parent_version_name: &String,
         new_version_name: String,
-        dependency_map_mux: DependencyMapMutex,
-    ) -> Result<(), CommandError> {
+        range: &str,
+        dependency_map_mux: &DependencyMapMutex,
+    ) {
         let mut dependency_map = dependency_map_mux.lock().unwrap();
-        let parents = parents_mux.lock().unwrap();
 
-        for parent_version_name in parents.iter() {
-            let parent_version = dependency_map
-                .entry(parent_version_name.to_string())
-                .or_insert(PackageLock::new(parent_version_name.ends_with(LATEST)));
+        let parent_version = dependency_map
+            .entry(parent_version_name.to_string())
+            .or_insert(PackageLock::new(parent_version_name.ends_with(LATEST)));
 
-            parent_version
-                .dependencies
-                .push(new_version_name.to_string());
+        let (package_name, _) = Versions::parse_raw_package_details(new_version_name.to_string());
+        parent_version
+            .ranges
+            .insert(package_name.to_string(), range.to_string());
+
+        // A dependency that is resolved again (such as one an override replaced) takes the place of the version it had before
+        let existing_version = parent_version.dependencies.iter().position(|dependency| {
+            Versions::parse_raw_package_details(dependency.to_string()).0 == package_name
+        });
+
+        match existing_version {
+            Some(index) => parent_version.dependencies[index] = new_version_name,
+            None => parent_version.dependencies.push(new_version_name),
         }
+    }
 
-        Ok(())
+    /// Records that a package failed to install, the error is reported for every top level package that depends on it.
+    pub fn record_failure(failure_map_mux: &FailureMapMutex, package: &str, err: CommandError) {
+        let mut failure_map = failure_map_mux.lock().unwrap();
+        failure_map.insert(package.to_string(), err.to_string());
     }
 
+    /// Installs a package and its dependencies.
     pub fn install_package(
         context: InstallContext,
         package_info: PackageInfo,
-        parents_mux: Arc<Mutex<Vec<String>>>,
     ) -> Result<(), CommandError> {
         if Self::already_resolved(&context, &package_info) {
             return Ok(());
         }
 
-        Self::append_version(
-            Arc::clone(&parents_mux),
-            package_info.stringified.to_string(),
-            Arc::clone(&context.dependency_map_mux),
-        )
-        .unwrap();
-
-        {
-            let mut parents = parents_mux.lock().unwrap();
-            parents.push(package_info.stringified.to_string());
-        }
-
         TaskAllocator::add_task(async move {
             let version_data = package_info.version_data;
 
-            let package_bytes =
-                HTTPRequest::get_bytes(context.client.clone(), version_data.dist.tarball)
-                    .await
-                    .unwrap();
+            // Linked packages aren't copied into the cache and they have their own node_modules, so there is nothing else to install
+            if version_data.dist.tarball.starts_with(LINK_PROTOCOL) {
+                let source = version_data
+                    .directory
+                    .expect("Linked packages always have a directory");
+
+                match Local::link(&package_info.stringified, &source) {
+                    Ok(()) => context
+                        .linked_directories_mux
+                        .lock()
+                        .unwrap()
+                        .push((package_info.stringified, source)),
+                    Err(err) => Self::record_failure(
+                        &context.failure_map_mux,
+                        &package_info.stringified,
+                        err,
+                    ),
+                }
+                return;
+            }
 
-            let package_destination = format!("{}/{}", *CACHE_DIRECTORY, package_info.stringified);
+            let bundled_dependencies = version_data.bundled_dependencies();
+
+            // Remote tarballs were already downloaded when their version data was read
+            let package_bytes = match version_data.tarball_bytes {
+                Some(tarball_bytes) => Ok(tarball_bytes),
+                None => Self::get_tarball(context.client.clone(), version_data.dist.tarball).await,
+            };
+
+            let package_bytes = match package_bytes {
+                Ok(package_bytes) => package_bytes,
+                Err(err) => {
+                    Self::record_failure(&context.failure_map_mux, &package_info.stringified, err);
+                    return;
+                }
+            };
+
+            // The tarball provides its bundled dependencies itself, installing them again could replace the versions the author bundled
+            let bundled_packages = match bundled_dependencies.is_empty() {
+                true => Ok(HashSet::new()),
+                false => util::bundled_packages(&package_bytes, &bundled_dependencies),
+            };
+
+            let bundled_packages = match bundled_packages {
+                Ok(bundled_packages) => bundled_packages,
+                Err(err) => {
+                    Self::record_failure(
+                        &context.failure_map_mux,
+                        &package_info.stringified,
+                        CommandError::ExtractionFailed(err),
+                    );
+                    return;
+                }
+            };
 
             // TODO(conaticus): Do this outside of tokio tasks as it's blocking the threads from working at full potential
             context
                 .bytes_sender
-                .send((package_destination, package_bytes))
+                .send((package_info.stringified.to_string(), package_bytes))
                 .unwrap();
 
-            let dependencies = version_data.dependencies.unwrap_or(HashMap::new());
-            Self::install_dependencies(parents_mux, context, dependencies).await;
+            // The registry lists optional dependencies in both fields, a package.json might only list them as optional
+            let optional_dependencies = version_data.optional_dependencies.unwrap_or_default();
+            let mut dependencies = version_data.dependencies.unwrap_or_default();
+            dependencies.extend(optional_dependencies.clone());
+            dependencies.retain(|name, _| !bundled_packages.contains(name));
+
+            Self::install_dependencies(
+                package_info.stringified,
+                context,
+                dependencies,
+                optional_dependencies.into_keys().collect(),
+                version_data.directory,
+            )
+            .await;
         });
 
         Ok(())
     }
 
+    /// Installs every dependency of a package, if one fails the failure is recorded against the parent and the rest are still installed.
+    /// Optional dependencies that fail are skipped instead, the parent works without them.
+    /// `directory` is the folder of a local parent, which its own local dependencies are relative to.
     async fn install_dependencies(
-        parents_mux: Arc<Mutex<Vec<String>>>,
+        parent: String,
         context: InstallContext,
         dependencies: HashMap<String, String>,
+        optional_dependencies: HashSet<String>,
+        directory: Option<PathBuf>,
     ) {
-        for (name, version) in dependencies {
-            let comparator = Versions::parse_semantic_version(&version)
-                .expect("Failed to parse semantic version"); // TODO(conaticus): Change this to return a result
-            let comparator = Some(&comparator);
+        for (name, version_range) in dependencies {
+            let result = Self::install_dependency(
+                &parent,
+                &context,
+                &name,
+                &version_range,
+                directory.as_deref(),
+            )
+            .await;
+
+            let Err(err) = result else {
+                continue;
+            };
 
-            let full_version = Versions::resolve_full_version(comparator);
-            let full_version = full_version.as_ref();
+            let dependency = format!("{name}@{version_range}");
+            match optional_dependencies.contains(&name) {
+                true => Self::skip_optional_dependency(&dependency, Some(&parent), &err),
+                false => {
+                    let err = CommandError::DependencyFailed(dependency, err.to_string());
+                    Self::record_failure(&context.failure_map_mux, &parent, err);
+                }
+            }
+        }
+    }
 
-            let (is_cached, cached_version) = Cache::exists(&name, full_version, comparator)
-                .await
-                .unwrap();
+    /// Reports an optional dependency that couldn't be installed, `parent` is None for the project's own optional dependencies.
+    /// Packages for other platforms are expected to be skipped, so they are only mentioned in verbose logs.
+    pub fn skip_optional_dependency(dependency: &str, parent: Option<&str>, err: &CommandError) {
+        let message = match parent {
+            Some(parent) => {
+                format!("Skipping optional dependency '{dependency}' of '{parent}' ({err})")
+            }
+            None => format!("Skipping optional dependency '{dependency}' ({err})"),
+        };
 
-            if is_cached {
-                let version = cached_version.expect("Could not resolve version of cached package");
-                let stringified = Versions::stringify(&name, &version);
+        match err {
+            CommandError::UnsupportedPlatform(_, _) => Logger::verbose(message),
+            _ => Logger::warn(message),
+        }
+    }
 
-                // We need to do this check as the pre-cached version may have just been installed (and therefore have no lockfile which is mandatory)
-                let dependency_map = context.dependency_map_mux.lock().unwrap();
-                if dependency_map.get(stringified.as_str()).is_none() {
-                    Cache::load_cached_version(stringified);
-                    continue;
+    /// Installs a peer dependency that the parent of a package doesn't provide.
+    /// It is added to the parent's dependencies, so it's linked next to the package that needs it.
+    pub async fn install_peer_dependency(
+        parent: &String,
+        context: &InstallContext,
+        name: &String,
+        version_range: &str,
+    ) {
+        // A cached parent's lock is loaded first, otherwise the lockfile written for it would only list the peer
+        {
+            let mut dependency_map = context.dependency_map_mux.lock().unwrap();
+            if !dependency_map.contains_key(parent) {
+                if let Ok(package_lock) = Cache::read_lockfile(parent) {
+                    dependency_map.insert(parent.to_string(), package_lock);
                 }
             }
+        }
 
-            let version_data =
-                Self::get_version_data(context.client.clone(), &name, full_version, comparator)
-                    .await
-                    .unwrap();
+        if let Err(err) = Self::install_dependency(parent, context, name, version_range, None).await
+        {
+            let err =
+                CommandError::DependencyFailed(format!("{name}@{version_range}"), err.to_string());
+            Self::record_failure(&context.failure_map_mux, parent, err);
+        }
+    }
+
+    async fn install_dependency(
+        parent: &String,
+        context: &InstallContext,
+        name: &String,
+        version_range: &str,
+        directory: Option<&Path>,
+    ) -> Result<(), CommandError> {
+        // Overrides replace the range before anything is resolved, so the version they replace is never fetched.
+        // The parent's lock still records the range it asked for, so it's known when the override is removed.
+        let overridden_range = context.overrides.apply(parent, name);
+
+        let version_spec = Versions::parse_dependency_version(
+            overridden_range.as_deref().unwrap_or(version_range),
+        )
+        .map_err(|err| CommandError::InvalidDependencyVersion(name.to_string(), err))?;
+        let (real_name, version_spec) = version_spec.real_package(name);
+
+        match version_spec {
+            VersionSpec::Git(git_spec) => {
+                let version_data = Self::get_git_version_data(git_spec).await?;
+                return Self::install_resolved(parent, context, name, version_range, version_data);
+            }
+            // A path in a registry or git package could point anywhere on the machine it's installed on
+            VersionSpec::Local(local_spec) => {
+                let directory = directory.ok_or(CommandError::UnexpectedLocalDependency(
+                    version_range.to_string(),
+                ))?;
+
+                let version_data = Local::version_data(local_spec, directory)?;
+                return Self::install_resolved(parent, context, name, version_range, version_data);
+            }
+            VersionSpec::Remote(remote_spec) => {
+                let version_data =
+                    Remote::version_data(context.client.clone(), remote_spec).await?;
+                return Self::install_resolved(parent, context, name, version_range, version_data);
+            }
+            _ => {}
+        }
+
+        let full_version = Versions::resolve_full_version(version_spec);
+        let full_version = full_version.as_ref();
+
+        // A version another package already depends on is reused before looking anywhere else
+        let resolved_version = match Self::find_resolved_version(context, real_name, version_spec) {
+            Some(version) => Some(version),
+            None => {
+                match Cache::exists(&real_name.to_string(), full_version, version_spec).await? {
+                    (true, cached_version) => {
+                        Some(cached_version.expect("Could not resolve version of cached package"))
+                    }
+                    (false, _) => None,
+                }
+            }
+        };
+
+        if let Some(version) = resolved_version {
+            Self::reuse_version(parent, context, name, real_name, &version, version_range);
+            return Ok(());
+        }
+
+        let version_data = Self::get_version_data(
+            context.client.clone(),
+            real_name,
+            full_version,
+            version_spec,
+        )
+        .await?;
+
+        // Dependencies are resolved at the same time, another one could have picked a matching version while the registry was being asked
+        match Self::find_resolved_version(context, real_name, version_spec) {
+            Some(version) => {
+                Self::reuse_version(parent, context, name, real_name, &version, version_range);
+                Ok(())
+            }
+            None => Self::install_resolved(parent, context, name, version_range, version_data),
+        }
+    }
+
+    /// Adds a version that is already resolved or cached to the dependencies of its parent.
+    /// It is linked along with its parent once everything has been installed.
+    fn reuse_version(
+        parent: &String,
+        context: &InstallContext,
+        name: &str,
+        real_name: &str,
+        version: &String,
+        version_range: &str,
+    ) {
+        let stringified = Versions::stringify(&real_name.to_string(), version);
 
-            let stringified = Versions::stringify(&name, &version_data.version);
+        Self::append_version(
+            parent,
+            Versions::stringify_alias(name, &stringified),
+            version_range,
+            &context.dependency_map_mux,
+        );
+        Self::apply_overrides(context, &stringified);
+    }
+
+    /// Applies the overrides to a package that was already in the cache, as its dependencies may have been resolved with other overrides or none.
+    /// A dependency is installed again if its version isn't allowed by its override, or by the range its parent asked for if it has none (as an override was removed).
+    /// Packages that are already in the dependency map are skipped, their dependencies are being resolved with the overrides.
+    pub fn apply_overrides(context: &InstallContext, package: &str) {
+        if context.overrides.is_empty() && !Cache::has_overridden_locks() {
+            return;
+        }
 
-            let package_info = PackageInfo {
-                version_data,
-                is_latest: Versions::is_latest(Some(&stringified)),
-                stringified,
+        let Ok(dependency_tree) = Cache::dependency_tree(Versions::real_package(package)) else {
+            return;
+        };
+
+        for package in dependency_tree {
+            let package = Versions::real_package(&package);
+
+            if context
+                .dependency_map_mux
+                .lock()
+                .unwrap()
+                .contains_key(&package)
+            {
+                continue;
+            }
+
+            let Ok(package_lock) = Cache::read_lockfile(&package) else {
+                continue;
             };
 
-            Self::install_package(context.clone(), package_info, Arc::clone(&parents_mux)).unwrap();
+            let mut replaced_dependencies = HashMap::new();
+
+            for dependency in package_lock.dependencies.iter() {
+                let (dependency_name, _) = Versions::parse_raw_package_details(dependency.clone());
+                let (_, version) =
+                    Versions::parse_raw_package_details(Versions::real_package(dependency));
+
+                let override_ = context.overrides.find(&package, &dependency_name);
+                let version_range = package_lock.ranges.get(&dependency_name);
+
+                let Some(allowed_range) = override_
+                    .map(|override_| &override_.version_range)
+                    .or(version_range)
+                else {
+                    continue;
+                };
+
+                let is_allowed = Versions::parse_dependency_version(allowed_range)
+                    .is_ok_and(|version_spec| version_spec.matches(&version));
+
+                match (is_allowed, override_) {
+                    (true, Some(override_)) => context.overrides.record(override_, &package),
+                    (true, None) => {}
+                    (false, _) => {
+                        replaced_dependencies
+                            .insert(dependency_name, version_range.cloned().unwrap_or_default());
+                    }
+                }
+            }
+
+            if replaced_dependencies.is_empty() {
+                continue;
+            }
+
+            let optional_dependencies =
+                package_lock.optional_dependencies.iter().cloned().collect();
+            context
+                .dependency_map_mux
+                .lock()
+                .unwrap()
+                .entry(package.to_string())
+                .or_insert(package_lock);
+
+            let context = context.clone();
+            TaskAllocator::add_task(async move {
+                Self::install_dependencies(
+                    package,
+                    context,
+                    replaced_dependencies,
+                    optional_dependencies,
+                    None,
+                )
+                .await;
+            });
+        }
+    }
+
+    /// Installs a dependency once the version to install has been resolved, unless it's already in the cache.
+    fn install_resolved(
+        parent: &String,
+        context: &InstallContext,
+        name: &str,
+        version_range: &str,
+        version_data: VersionData,
+    ) -> Result<(), CommandError> {
+        Platform::check(&version_data)?;
+        let stringified = Versions::stringify(&version_data.name, &version_data.version);
+
+        // This is done even if the package is already resolved so every package lock lists all of its dependencies
+        Self::append_version(
+            parent,
+            Versions::stringify_alias(name, &stringified),
+            version_range,
+            &context.dependency_map_mux,
+        );
+
+        // Git and remote packages can only be looked up in the cache once their commit or tarball has been resolved
+        if Cache::is_installed(&stringified) {
+            Self::apply_overrides(context, &stringified);
+            return Ok(());
         }
+
+        let package_info = PackageInfo {
+            version_data,
+            is_latest: Versions::is_latest(Some(&stringified)),
+            stringified,
+        };
+
+        Self::install_package(context.clone(), package_info)
     }
 
     /// Creates the node modules folder if it is not present.
@@ -0,0 +1,146 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    cache::Cache,
+    errors::CommandError,
+    package_json::PackageJson,
+    types::VersionData,
+    versions::{FILE_BUILD_PREFIX, LINK_BUILD_PREFIX},
+};
+
+pub const FILE_PROTOCOL: &str = "file:";
+pub const LINK_PROTOCOL: &str = "link:";
+
+/// `file:` packages are copied into the cache, `link:` packages point straight at their folder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LocalProtocol {
+    File,
+    Link,
+}
+
+impl LocalProtocol {
+    fn prefix(&self) -> &'static str {
+        match self {
+            LocalProtocol::File => FILE_PROTOCOL,
+            LocalProtocol::Link => LINK_PROTOCOL,
+        }
+    }
+
+    fn build_prefix(&self) -> &'static str {
+        match self {
+            LocalProtocol::File => FILE_BUILD_PREFIX,
+            LocalProtocol::Link => LINK_BUILD_PREFIX,
+        }
+    }
+}
+
+/// A package in a folder on this machine, `path` is relative to the folder of the package.json it was listed in.
+#[derive(Clone, Debug)]
+pub struct LocalSpec {
+    pub protocol: LocalProtocol,
+    pub path: String,
+}
+
+impl LocalSpec {
+    /// Parses `file:path` and `link:path`, None is returned if `raw_spec` isn't either.
+    pub fn parse(raw_spec: &str) -> Option<Self> {
+        let (protocol, path) = match raw_spec.strip_prefix(FILE_PROTOCOL) {
+            Some(path) => (LocalProtocol::File, path),
+            None => (LocalProtocol::Link, raw_spec.strip_prefix(LINK_PROTOCOL)?),
+        };
+
+        match path.is_empty() {
+            true => None,
+            false => Some(Self {
+                protocol,
+                path: path.to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for LocalSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.protocol.prefix(), self.path)
+    }
+}
+
+pub struct Local;
+impl Local {
+    /// Reads the version data of a local package, `directory` is the folder of the package.json it was listed in.
+    /// The folder is kept in the build metadata (1.0.0+file.home-me-project-shared) so packages from different folders don't share a cache entry.
+    pub fn version_data(
+        local_spec: &LocalSpec,
+        directory: &Path,
+    ) -> Result<VersionData, CommandError> {
+        let not_found = |err| CommandError::LocalPackageNotFound(local_spec.path.to_string(), err);
+
+        let source = directory
+            .join(&local_spec.path)
+            .canonicalize()
+            .map_err(not_found)?;
+        if !source.is_dir() {
+            return Err(not_found(io::Error::new(
+                ErrorKind::InvalidInput,
+                "not a directory",
+            )));
+        }
+
+        let package_json = PackageJson::read_from(&source.join("package.json"))?;
+        let name = package_json
+            .name
+            .clone()
+            .ok_or(CommandError::MissingPackageName(
+                source.display().to_string(),
+            ))?;
+
+        let version = package_json
+            .version
+            .clone()
+            .unwrap_or(String::from("0.0.0"));
+        let (version, _) = version.split_once('+').unwrap_or((&version, ""));
+
+        // Build metadata can only contain alphanumerics and hyphens
+        let folder = source
+            .to_string_lossy()
+            .chars()
+            .map(|char| match char.is_ascii_alphanumeric() {
+                true => char,
+                false => '-',
+            })
+            .collect::<String>();
+
+        let version = format!(
+            "{version}+{}{}",
+            local_spec.protocol.build_prefix(),
+            folder.trim_matches('-')
+        );
+        let tarball = format!("{}{}", local_spec.protocol.prefix(), source.display());
+
+        Ok(VersionData {
+            directory: Some(source),
+            ..VersionData::from_package_json(package_json, name, version, tarball)
+        })
+    }
+
+    /// Links a local package into the cache, it is a symlink to the folder rather than a copy so changes show up straight away.
+    /// Its dependencies have to be installed into its own node_modules, as node looks for them next to the folder the link points to.
+    /// That is left to the install, see InstallHandler::install_linked_dependencies().
+    pub fn link(stringified: &str, source: &Path) -> Result<(), CommandError> {
+        let package_directory = Cache::package_directory(stringified);
+        let package_link = format!("{package_directory}/package");
+
+        // Only the symlink is replaced, the lockfile next to it stays until a new one is written in case installing the dependencies fails
+        if fs::symlink_metadata(&package_link).is_ok() {
+            symlink::remove_symlink_dir(&package_link).map_err(CommandError::FailedToRemoveFile)?;
+        }
+
+        fs::create_dir_all(&package_directory).map_err(CommandError::FailedToCreateFile)?;
+        symlink::symlink_dir(source, package_link).map_err(CommandError::FailedToCreateFile)
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{config::Config, errors::ParseError};
+
+#[derive(Clone, Copy, Default, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    Silent,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Verbose,
+}
+
+impl FromStr for LogLevel {
+    type Err = ParseError;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level {
+            "silent" => Ok(LogLevel::Silent),
+            "error" => Ok(LogLevel::Error),
+            "warn" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "verbose" => Ok(LogLevel::Verbose),
+            _ => Err(ParseError::InvalidArgument(
+                String::from("--loglevel"),
+                level.to_string(),
+            )),
+        }
+    }
+}
+
+/// Progress and diagnostic messages go through here so they respect `--loglevel`.
+/// The actual output of a command (tables, trees, json) should still be printed directly.
+pub struct Logger;
+impl Logger {
+    pub fn error(message: impl Display) {
+        if Self::is_enabled(LogLevel::Error) {
+            eprintln!("{message}");
+        }
+    }
+
+    pub fn warn(message: impl Display) {
+        if Self::is_enabled(LogLevel::Warn) {
+            eprintln!("Warning: {message}");
+        }
+    }
+
+    pub fn info(message: impl Display) {
+        if Self::is_enabled(LogLevel::Info) {
+            println!("{message}");
+        }
+    }
+
+    pub fn verbose(message: impl Display) {
+        if Self::is_enabled(LogLevel::Verbose) {
+            println!("{message}");
+        }
+    }
+
+    fn is_enabled(level: LogLevel) -> bool {
+        Config::get().log_level >= level
+    }
+}
@@ -1,20 +1,30 @@
 mod cache;
 mod commands;
+mod config;
 mod errors;
+mod git;
 mod http;
 mod installer;
+mod local;
+mod logger;
+mod node_modules;
+mod overrides;
+mod package_json;
+mod peers;
+mod platform;
+mod remote;
 mod types;
 mod util;
 mod versions;
 
 use std::env;
 
-use commands::command_handler;
+use commands::{cli::Cli, command_handler};
 
 #[tokio::main]
 async fn main() {
     let parse_result = command_handler::handle_args(env::args()).await;
     if let Err(err) = parse_result {
-        println!("Failed to parse command: {err}");
+        Cli::exit_with_error(format!("Failed to parse command: {err}"));
     }
 }
@@ -0,0 +1,312 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use crate::{
+    cache::{Cache, CACHE_DIRECTORY},
+    errors::CommandError,
+    package_json::{DependencyType, PackageJson, PACKAGE_JSON_PATH},
+    types::ProjectLock,
+    versions::Versions,
+};
+
+pub const NODE_MODULES_PATH: &str = "./node_modules";
+pub const PROJECT_LOCK_PATH: &str = "./node_modules/.click-lock.json";
+
+pub struct NodeModules;
+impl NodeModules {
+    /// Returns a hashmap of every package linked into the node modules folder,
+    /// each key is the package name and the value is the cached package (package@version) it points to.
+    /// Packages linked under an alias are formatted as alias@npm:package@version.
+    /// Anything that isn't a symlink into the cache is ignored as it wasn't installed by us.
+    pub fn linked_packages() -> Result<HashMap<String, String>, CommandError> {
+        let mut linked_packages = HashMap::new();
+
+        if !Path::new(NODE_MODULES_PATH).exists() {
+            return Ok(linked_packages);
+        }
+
+        let entries =
+            fs::read_dir(NODE_MODULES_PATH).map_err(CommandError::FailedDirectoryEntry)?;
+
+        for entry in entries {
+            let entry = entry.map_err(CommandError::FailedDirectoryEntry)?;
+            let package_name = entry.file_name().to_string_lossy().to_string();
+
+            if let Some(cached_package) = Self::cached_package(&entry.path()) {
+                let cached_package = Versions::stringify_alias(&package_name, &cached_package);
+                linked_packages.insert(package_name, cached_package);
+                continue;
+            }
+
+            // Scoped packages are linked one level deeper, inside of a folder named after the scope
+            if package_name.starts_with('@') && entry.path().is_dir() {
+                let scoped_entries =
+                    fs::read_dir(entry.path()).map_err(CommandError::FailedDirectoryEntry)?;
+
+                for scoped_entry in scoped_entries {
+                    let scoped_entry = scoped_entry.map_err(CommandError::FailedDirectoryEntry)?;
+                    let scoped_name = scoped_entry.file_name().to_string_lossy().to_string();
+
+                    if let Some(cached_package) = Self::cached_package(&scoped_entry.path()) {
+                        let scoped_name = format!("{package_name}/{scoped_name}");
+                        let cached_package =
+                            Versions::stringify_alias(&scoped_name, &cached_package);
+                        linked_packages.insert(scoped_name, cached_package);
+                    }
+                }
+            }
+        }
+
+        Ok(linked_packages)
+    }
+
+    /// Linked packages which are not a dependency of any other linked package.
+    /// This is used to find the direct dependencies of a project when there is no package.json.
+    pub fn top_level_packages(
+        linked_packages: &HashMap<String, String>,
+    ) -> Result<HashSet<String>, CommandError> {
+        let mut dependency_names = HashSet::new();
+
+        for cached_package in linked_packages.values() {
+            for dependency in Cache::read_lockfile(cached_package)?.dependencies {
+                let (dependency_name, _) = Versions::parse_raw_package_details(dependency);
+                dependency_names.insert(dependency_name);
+            }
+        }
+
+        Ok(linked_packages
+            .keys()
+            .filter(|package_name| !dependency_names.contains(*package_name))
+            .cloned()
+            .collect())
+    }
+
+    /// The names of the linked packages that the root packages need, the root packages included.
+    pub fn required_packages(
+        linked_packages: &HashMap<String, String>,
+        root_packages: &HashSet<String>,
+    ) -> Result<HashSet<String>, CommandError> {
+        let mut required_packages = HashSet::new();
+
+        for (package_name, cached_package) in linked_packages {
+            if !root_packages.contains(package_name) {
+                continue;
+            }
+
+            for dependency in Cache::dependency_tree(cached_package.to_string())? {
+                let (dependency_name, _) = Versions::parse_raw_package_details(dependency);
+                required_packages.insert(dependency_name);
+            }
+        }
+
+        Ok(required_packages)
+    }
+
+    /// The packages that everything else linked is kept for, the dependencies in the package.json other than `removed_packages`.
+    /// If there is no package.json, every linked package that isn't a dependency of another linked package is one.
+    /// Peer dependencies that were installed automatically are included for as long as a package that needs them is.
+    pub fn root_packages(
+        linked_packages: &HashMap<String, String>,
+        removed_packages: &[String],
+    ) -> Result<HashSet<String>, CommandError> {
+        let mut root_packages = match Path::new(PACKAGE_JSON_PATH).exists() {
+            true => PackageJson::read()?
+                .all_dependencies()
+                .into_keys()
+                .collect(),
+            false => Self::top_level_packages(linked_packages)?,
+        };
+
+        root_packages.retain(|package_name| !removed_packages.contains(package_name));
+        let installed_peers = Self::read_lock()?.peers;
+        root_packages.extend(Self::required_peers(
+            linked_packages,
+            &root_packages,
+            &installed_peers,
+        )?);
+
+        Ok(root_packages)
+    }
+
+    /// The installed peer dependencies (see ProjectLock) that the root packages, or anything they need, list as a peer.
+    /// Peers can have peers of their own, so this repeats until no more are found.
+    fn required_peers(
+        linked_packages: &HashMap<String, String>,
+        root_packages: &HashSet<String>,
+        installed_peers: &[String],
+    ) -> Result<HashSet<String>, CommandError> {
+        let mut installed_peers = installed_peers.to_vec();
+        let mut root_packages = root_packages.clone();
+        let mut required_peers = HashSet::new();
+
+        loop {
+            let mut peer_names = HashSet::new();
+
+            for package_name in Self::required_packages(linked_packages, &root_packages)? {
+                if let Some(cached_package) = linked_packages.get(&package_name) {
+                    peer_names.extend(
+                        Cache::read_lockfile(cached_package)?
+                            .peer_dependencies
+                            .into_keys(),
+                    );
+                }
+            }
+
+            let (found_peers, remaining_peers) = installed_peers
+                .into_iter()
+                .partition::<Vec<_>, _>(|peer_name| peer_names.contains(peer_name));
+
+            if found_peers.is_empty() {
+                return Ok(required_peers);
+            }
+
+            root_packages.extend(found_peers.iter().cloned());
+            required_peers.extend(found_peers);
+            installed_peers = remaining_peers;
+        }
+    }
+
+    /// Linked packages that are only there because of the devDependencies in the package.json.
+    /// Top level packages that aren't in the package.json are treated as regular dependencies, so they are never counted.
+    /// Installed peer dependencies are the exception, they only count as regular if a regular dependency needs them.
+    pub fn dev_only_packages(
+        linked_packages: &HashMap<String, String>,
+        installed_peers: &[String],
+    ) -> Result<Vec<String>, CommandError> {
+        if !Path::new(PACKAGE_JSON_PATH).exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut dev_packages = HashSet::new();
+        let mut root_packages = Self::top_level_packages(linked_packages)?;
+        root_packages.retain(|package_name| !installed_peers.contains(package_name));
+
+        for (package_name, (dependency_type, _)) in PackageJson::read()?.typed_dependencies() {
+            match dependency_type == DependencyType::Dev {
+                true => {
+                    root_packages.remove(&package_name);
+                    dev_packages.insert(package_name);
+                }
+                false => {
+                    root_packages.insert(package_name);
+                }
+            }
+        }
+
+        root_packages.extend(Self::required_peers(
+            linked_packages,
+            &root_packages,
+            installed_peers,
+        )?);
+        dev_packages.extend(Self::required_peers(
+            linked_packages,
+            &dev_packages,
+            installed_peers,
+        )?);
+
+        let required_packages = Self::required_packages(linked_packages, &root_packages)?;
+        let mut dev_only = Self::required_packages(linked_packages, &dev_packages)?
+            .into_iter()
+            .filter(|package_name| !required_packages.contains(package_name))
+            .collect::<Vec<_>>();
+
+        dev_only.sort();
+        Ok(dev_only)
+    }
+
+    /// Reads the project lock, a project that hasn't written one yet gets an empty lock.
+    pub fn read_lock() -> Result<ProjectLock, CommandError> {
+        let project_lock_raw = match fs::read_to_string(PROJECT_LOCK_PATH) {
+            Ok(project_lock_raw) => project_lock_raw,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ProjectLock::default()),
+            Err(err) => return Err(CommandError::FailedToReadPackageLock(err)),
+        };
+
+        serde_json::from_str::<ProjectLock>(&project_lock_raw)
+            .map_err(CommandError::FailedToParsePackageLock)
+    }
+
+    /// Records why the packages in the node modules folder are there, this should be called whenever packages are linked or unlinked.
+    /// `installed_peers` are the names of peer dependencies that were just installed, they are added to the ones the lock already has.
+    pub fn write_lock(installed_peers: &[String]) -> Result<(), CommandError> {
+        if !Path::new(NODE_MODULES_PATH).exists() {
+            return Ok(());
+        }
+
+        let linked_packages = Self::linked_packages()?;
+
+        let mut peers = Self::read_lock()?.peers;
+        peers.extend(installed_peers.iter().cloned());
+        peers.retain(|peer_name| linked_packages.contains_key(peer_name));
+        peers.sort();
+        peers.dedup();
+        let mut overridden_locks = Cache::overridden_locks();
+
+        // Overridden locks of packages that are no longer linked aren't needed anymore
+        if !overridden_locks.is_empty() {
+            let mut linked_tree = HashSet::new();
+            for cached_package in linked_packages.values() {
+                linked_tree.extend(
+                    Cache::dependency_tree(cached_package.to_string())?
+                        .iter()
+                        .map(|package| Versions::real_package(package)),
+                );
+            }
+
+            overridden_locks.retain(|package, _| linked_tree.contains(package));
+        }
+
+        let project_lock = ProjectLock {
+            dev_only: Self::dev_only_packages(&linked_packages, &peers)?,
+            peers,
+            overridden_locks,
+        };
+
+        let project_lock_string = serde_json::to_string(&project_lock)
+            .map_err(CommandError::FailedToSerializePackageLock)?;
+
+        fs::write(PROJECT_LOCK_PATH, project_lock_string).map_err(CommandError::FailedToWriteFile)
+    }
+
+    /// Removes the symlink of a package from the node modules folder.
+    /// The scope folder of a scoped package is removed too once nothing is left in it.
+    pub fn unlink(package_name: &str) -> Result<(), CommandError> {
+        symlink::remove_symlink_dir(format!("{NODE_MODULES_PATH}/{package_name}"))
+            .map_err(CommandError::FailedToRemoveFile)?;
+
+        if let Some((scope, _)) = package_name.split_once('/') {
+            let scope_path = format!("{NODE_MODULES_PATH}/{scope}");
+
+            let is_empty = fs::read_dir(&scope_path)
+                .map_err(CommandError::FailedDirectoryEntry)?
+                .next()
+                .is_none();
+
+            if is_empty {
+                fs::remove_dir(scope_path).map_err(CommandError::FailedToRemoveFile)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Links are formatted as {cache}/package@version/package
+    fn cached_package(link_path: &Path) -> Option<String> {
+        let target = fs::read_link(link_path).ok()?;
+        let cached_package_dir = target.parent()?;
+
+        if cached_package_dir.parent()? != Path::new(CACHE_DIRECTORY.as_str()) {
+            return None;
+        }
+
+        let directory_name = cached_package_dir
+            .file_name()?
+            .to_string_lossy()
+            .to_string();
+        Some(Cache::package_from_directory(directory_name))
+    }
+}
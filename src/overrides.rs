@@ -0,0 +1,232 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::Path,
+    sync::Mutex,
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    errors::CommandError,
+    package_json::{PackageJson, PACKAGE_JSON_PATH},
+    versions::Versions,
+};
+
+// Separates the parent from the package in the keys of npm's overrides, `foo>bar` only overrides bar where foo depends on it.
+const PARENT_SEPARATOR: char = '>';
+
+// The key of a nested npm override that overrides the parent itself, `{ "foo": { ".": "1.0.0", "bar": "2.0.0" } }`.
+const SELF_KEY: &str = ".";
+
+// Yarn resolutions that start with this apply to the package wherever it is depended on.
+const ANY_PARENT: &str = "**";
+
+/// A dependency that is installed with another version range, everywhere or only where `parent` depends on it.
+/// Only the direct parent is checked, `foo>bar>baz` overrides baz wherever bar depends on it.
+pub struct Override {
+    // The key in the package.json, used when the override is reported.
+    pub key: String,
+    pub parent: Option<String>,
+    pub name: String,
+    pub version_range: String,
+}
+
+/// The overrides of the project, read from npm's `overrides` and yarn's `resolutions` in the package.json.
+/// They don't apply to the project's own dependencies, only to the dependencies of packages.
+#[derive(Default)]
+pub struct Overrides {
+    overrides: Vec<Override>,
+    // Each override that was used along with the package it was used for, reported once the install is done.
+    applied: Mutex<BTreeSet<(String, String, String)>>,
+    // Packages that had a dependency replaced, their locks only apply to this project.
+    overridden_packages: Mutex<HashSet<String>>,
+}
+
+impl Overrides {
+    /// Reads the overrides from the package.json in the working directory, a project without one has no overrides.
+    pub fn read() -> Result<Self, CommandError> {
+        if !Path::new(PACKAGE_JSON_PATH).exists() {
+            return Ok(Self::default());
+        }
+
+        let package_json = PackageJson::read()?;
+        let mut overrides = Self::default();
+
+        if let Some(npm_overrides) = &package_json.overrides {
+            overrides.add_npm_overrides(&package_json, None, npm_overrides)?;
+        }
+
+        for (key, version_range) in package_json.resolutions.iter().flatten() {
+            overrides.add_yarn_resolution(key, version_range)?;
+        }
+
+        Ok(overrides)
+    }
+
+    /// npm overrides are either a version range or an object of overrides that only apply below that package.
+    /// A range starting with `$` refers to the range the project itself depends on the package with.
+    fn add_npm_overrides(
+        &mut self,
+        package_json: &PackageJson,
+        parent_key: Option<&str>,
+        npm_overrides: &Map<String, Value>,
+    ) -> Result<(), CommandError> {
+        for (key, value) in npm_overrides {
+            let full_key = match parent_key {
+                Some(parent_key) if key == SELF_KEY => parent_key.to_string(),
+                Some(parent_key) => format!("{parent_key}{PARENT_SEPARATOR}{key}"),
+                None => key.to_string(),
+            };
+
+            match value {
+                Value::String(version_range) => {
+                    let version_range = match version_range.strip_prefix('$') {
+                        Some(reference) => package_json
+                            .all_dependencies()
+                            .remove(reference)
+                            .ok_or(CommandError::InvalidOverride(
+                                full_key.to_string(),
+                                format!("'{reference}' is not a dependency of the project"),
+                            ))?,
+                        None => version_range.to_string(),
+                    };
+
+                    let mut path = full_key.split(PARENT_SEPARATOR).collect::<Vec<_>>();
+                    let name = path.pop().unwrap_or_default();
+                    self.add(&full_key, path.pop(), name, &version_range)?;
+                }
+                Value::Object(nested_overrides) => {
+                    self.add_npm_overrides(package_json, Some(&full_key), nested_overrides)?
+                }
+                _ => {
+                    return Err(CommandError::InvalidOverride(
+                        full_key,
+                        String::from("it has to be a version range or an object"),
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Yarn resolutions are keyed by a path of package names, such as `foo/bar`, `**/bar` or `@scope/foo/bar`.
+    fn add_yarn_resolution(&mut self, key: &str, version_range: &str) -> Result<(), CommandError> {
+        let mut path: Vec<String> = Vec::new();
+
+        for segment in key.split('/') {
+            match path.last_mut() {
+                // The segment after a scope is the name of the scoped package
+                Some(scope) if scope.starts_with('@') && !scope.contains('/') => {
+                    scope.push('/');
+                    scope.push_str(segment);
+                }
+                _ => path.push(segment.to_string()),
+            }
+        }
+
+        let (name, parents) = path.split_last().ok_or(CommandError::InvalidOverride(
+            key.to_string(),
+            String::from("it has no package name"),
+        ))?;
+
+        let parent = parents
+            .last()
+            .map(String::as_str)
+            .filter(|parent| *parent != ANY_PARENT);
+
+        self.add(key, parent, name, version_range)
+    }
+
+    fn add(
+        &mut self,
+        key: &str,
+        parent: Option<&str>,
+        name: &str,
+        version_range: &str,
+    ) -> Result<(), CommandError> {
+        let invalid_override =
+            |reason: &str| CommandError::InvalidOverride(key.to_string(), reason.to_string());
+
+        if name.is_empty() || parent.is_some_and(str::is_empty) {
+            return Err(invalid_override("it has an empty package name"));
+        }
+
+        // `foo@1.0.0` only overrides some versions of foo in npm, which can't be known until it's resolved
+        let is_selector = |package_name: &str| package_name.chars().skip(1).any(|char| char == '@');
+        if is_selector(name) || parent.is_some_and(is_selector) {
+            return Err(invalid_override("version selectors are not supported"));
+        }
+
+        Versions::parse_dependency_version(version_range)
+            .map_err(|err| CommandError::InvalidDependencyVersion(name.to_string(), err))?;
+
+        self.overrides.push(Override {
+            key: key.to_string(),
+            parent: parent.map(str::to_string),
+            name: name.to_string(),
+            version_range: version_range.to_string(),
+        });
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Finds the override for a dependency of `parent` (package@version), ones for that parent win over ones for every parent.
+    pub fn find(&self, parent: &str, name: &str) -> Option<&Override> {
+        let (parent_name, _) = Versions::parse_raw_package_details(parent.to_string());
+
+        self.overrides
+            .iter()
+            .filter(|override_| {
+                override_.name == name
+                    && override_
+                        .parent
+                        .as_ref()
+                        .is_none_or(|parent| parent == &parent_name)
+            })
+            .max_by_key(|override_| override_.parent.is_some())
+    }
+
+    /// Returns the version range a dependency of `parent` should be installed with if it's overridden.
+    /// The parent is recorded as overridden, as its lock no longer matches the one any other project would have.
+    pub fn apply(&self, parent: &str, name: &str) -> Option<String> {
+        let override_ = self.find(parent, name)?;
+
+        self.record(override_, parent);
+        self.overridden_packages
+            .lock()
+            .unwrap()
+            .insert(parent.to_string());
+
+        Some(override_.version_range.to_string())
+    }
+
+    /// Records that an override was used for a package, so it is reported once the install is done.
+    pub fn record(&self, override_: &Override, parent: &str) {
+        self.applied.lock().unwrap().insert((
+            override_.key.to_string(),
+            override_.version_range.to_string(),
+            parent.to_string(),
+        ));
+    }
+
+    /// A line for each override that was used and the package it was used for.
+    pub fn applied(&self) -> Vec<String> {
+        let applied = self.applied.lock().unwrap();
+
+        applied
+            .iter()
+            .map(|(key, version_range, parent)| {
+                format!("Applied override '{key}' ({version_range}) to '{parent}'")
+            })
+            .collect()
+    }
+
+    pub fn overridden_packages(&self) -> HashSet<String> {
+        self.overridden_packages.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, fs, io::ErrorKind, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{ser::PrettyFormatter, Map, Serializer, Value};
+
+use crate::{
+    errors::CommandError,
+    types::{BundleDependencies, PeerDependencyMeta},
+};
+
+pub const PACKAGE_JSON_PATH: &str = "./package.json";
+
+const DEFAULT_INDENT: &str = "  ";
+
+type Dependencies = HashMap<String, String>;
+
+// Only the fields we need for installing are included at the moment.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageJson {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: Option<Dependencies>,
+    pub dev_dependencies: Option<Dependencies>,
+    pub optional_dependencies: Option<Dependencies>,
+    pub peer_dependencies: Option<Dependencies>,
+    pub peer_dependencies_meta: Option<HashMap<String, PeerDependencyMeta>>,
+    pub scripts: Option<HashMap<String, String>>,
+    pub os: Option<Vec<String>>,
+    pub cpu: Option<Vec<String>>,
+    pub libc: Option<Vec<String>>,
+    #[serde(alias = "bundledDependencies")]
+    pub bundle_dependencies: Option<BundleDependencies>,
+    // npm's overrides and yarn's resolutions, see Overrides::read().
+    pub overrides: Option<Map<String, Value>>,
+    pub resolutions: Option<HashMap<String, String>>,
+}
+
+impl PackageJson {
+    /// Reads the package.json in the working directory.
+    pub fn read() -> Result<Self, CommandError> {
+        Self::read_from(Path::new(PACKAGE_JSON_PATH))
+    }
+
+    pub fn read_from(path: &Path) -> Result<Self, CommandError> {
+        let package_json_raw =
+            fs::read_to_string(path).map_err(CommandError::FailedToReadPackageJson)?;
+
+        Self::parse(&package_json_raw)
+    }
+
+    pub fn parse(package_json_raw: &str) -> Result<Self, CommandError> {
+        serde_json::from_str::<PackageJson>(package_json_raw)
+            .map_err(CommandError::FailedToParsePackageJson)
+    }
+
+    /// Returns the dependencies declared in a single dependency field.
+    pub fn dependencies_of(&self, dependency_type: DependencyType) -> Dependencies {
+        let dependencies = match dependency_type {
+            DependencyType::Regular => &self.dependencies,
+            DependencyType::Dev => &self.dev_dependencies,
+            DependencyType::Optional => &self.optional_dependencies,
+            DependencyType::Peer => &self.peer_dependencies,
+        };
+
+        dependencies.clone().unwrap_or_default()
+    }
+
+    /// Merges every installable dependency field into a single map of package name to its dependency type and version.
+    /// If a package is declared more than once, the same precedence as npm is used (optional > regular > dev).
+    pub fn typed_dependencies(&self) -> HashMap<String, (DependencyType, String)> {
+        let mut typed_dependencies = HashMap::new();
+
+        for dependency_type in DependencyType::INSTALLABLE {
+            for (package_name, version) in self.dependencies_of(dependency_type) {
+                typed_dependencies.insert(package_name, (dependency_type, version));
+            }
+        }
+
+        typed_dependencies
+    }
+
+    /// Merges every installable dependency field into a single map of package name to version.
+    pub fn all_dependencies(&self) -> Dependencies {
+        self.typed_dependencies()
+            .into_iter()
+            .map(|(package_name, (_, version))| (package_name, version))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum DependencyType {
+    #[default]
+    Regular,
+    Dev,
+    Optional,
+    Peer,
+}
+
+impl DependencyType {
+    pub const ALL: [DependencyType; 4] = [
+        DependencyType::Regular,
+        DependencyType::Dev,
+        DependencyType::Optional,
+        DependencyType::Peer,
+    ];
+
+    /// Dependency types that get installed, in order of increasing precedence.
+    pub const INSTALLABLE: [DependencyType; 3] = [
+        DependencyType::Dev,
+        DependencyType::Regular,
+        DependencyType::Optional,
+    ];
+
+    /// The key of the package.json field that holds this type of dependency.
+    pub fn field_name(&self) -> &'static str {
+        match self {
+            DependencyType::Regular => "dependencies",
+            DependencyType::Dev => "devDependencies",
+            DependencyType::Optional => "optionalDependencies",
+            DependencyType::Peer => "peerDependencies",
+        }
+    }
+}
+
+/// A package.json which can be modified and written back without losing its key order or indentation.
+pub struct PackageJsonEditor {
+    contents: Map<String, Value>,
+    indent: String,
+    trailing_newline: bool,
+}
+
+impl PackageJsonEditor {
+    /// Opens the package.json in the working directory, or starts an empty one if it doesn't exist yet.
+    pub fn open() -> Result<Self, CommandError> {
+        let package_json_raw = match fs::read_to_string(PACKAGE_JSON_PATH) {
+            Ok(package_json_raw) => package_json_raw,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(Self {
+                    contents: Map::new(),
+                    indent: DEFAULT_INDENT.to_string(),
+                    trailing_newline: true,
+                })
+            }
+            Err(err) => return Err(CommandError::FailedToReadPackageJson(err)),
+        };
+
+        let contents = serde_json::from_str::<Map<String, Value>>(&package_json_raw)
+            .map_err(CommandError::FailedToParsePackageJson)?;
+
+        Ok(Self {
+            contents,
+            indent: Self::detect_indent(&package_json_raw),
+            trailing_newline: package_json_raw.ends_with('\n'),
+        })
+    }
+
+    /// Sets the version of a dependency, moving it out of any other dependency field it was previously declared in.
+    /// If the dependency is already in the field its position is kept, otherwise it is appended to the end.
+    pub fn set_dependency(
+        &mut self,
+        dependency_type: DependencyType,
+        package_name: &str,
+        version: &str,
+    ) {
+        for other_type in DependencyType::ALL {
+            if other_type != dependency_type {
+                self.remove_from_field(other_type.field_name(), package_name);
+            }
+        }
+
+        let dependencies = self
+            .contents
+            .entry(dependency_type.field_name())
+            .or_insert_with(|| Value::Object(Map::new()));
+
+        if !dependencies.is_object() {
+            *dependencies = Value::Object(Map::new());
+        }
+
+        if let Value::Object(dependencies) = dependencies {
+            dependencies.insert(package_name.to_string(), Value::String(version.to_string()));
+        }
+    }
+
+    pub fn field(&self, field_name: &str) -> Option<&Value> {
+        self.contents.get(field_name)
+    }
+
+    /// Sets a top level field, a field that already exists keeps its position.
+    pub fn set_field(&mut self, field_name: &str, value: Value) {
+        self.contents.insert(field_name.to_string(), value);
+    }
+
+    /// Removes a dependency from every dependency field, returns true if it was declared anywhere.
+    pub fn remove_dependency(&mut self, package_name: &str) -> bool {
+        let mut removed = false;
+
+        for dependency_type in DependencyType::ALL {
+            removed |= self.remove_from_field(dependency_type.field_name(), package_name);
+        }
+
+        removed
+    }
+
+    /// Formats the package.json exactly as save() would write it.
+    pub fn to_pretty_string(&self) -> Result<String, CommandError> {
+        let mut package_json_raw = Vec::new();
+        let formatter = PrettyFormatter::with_indent(self.indent.as_bytes());
+        let mut serializer = Serializer::with_formatter(&mut package_json_raw, formatter);
+
+        self.contents
+            .serialize(&mut serializer)
+            .map_err(CommandError::FailedToSerializePackageJson)?;
+
+        if self.trailing_newline {
+            package_json_raw.push(b'\n');
+        }
+
+        // serde_json only ever writes valid utf-8
+        Ok(String::from_utf8(package_json_raw).unwrap())
+    }
+
+    pub fn save(&self) -> Result<(), CommandError> {
+        fs::write(PACKAGE_JSON_PATH, self.to_pretty_string()?)
+            .map_err(CommandError::FailedToWriteFile)
+    }
+
+    /// Removes a dependency from a field, returns true if it was present.
+    /// `Map::remove` does not keep the key order, which is why `retain` is used instead.
+    fn remove_from_field(&mut self, field_name: &str, package_name: &str) -> bool {
+        let Some(Value::Object(dependencies)) = self.contents.get_mut(field_name) else {
+            return false;
+        };
+
+        let previous_len = dependencies.len();
+        dependencies.retain(|name, _| name != package_name);
+
+        previous_len != dependencies.len()
+    }
+
+    /// Uses the leading whitespace of the first indented line, this is the indentation of the top level keys.
+    fn detect_indent(package_json_raw: &str) -> String {
+        package_json_raw
+            .lines()
+            .skip(1)
+            .map(|line| {
+                line.chars()
+                    .take_while(|char| *char == ' ' || *char == '\t')
+                    .collect::<String>()
+            })
+            .find(|indent| !indent.is_empty())
+            .unwrap_or(DEFAULT_INDENT.to_string())
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    cache::Cache,
+    errors::CommandError,
+    types::{DependencyMap, PackageLock},
+    versions::Versions,
+};
+
+/// A peer dependency that the parent of a package doesn't provide, `parent` is None when it's the project.
+pub struct MissingPeer {
+    pub parent: Option<String>,
+    pub name: String,
+    pub range: String,
+}
+
+#[derive(Default)]
+pub struct PeerCheck {
+    pub missing: Vec<MissingPeer>,
+    pub conflicts: Vec<CommandError>,
+}
+
+pub struct Peers;
+impl Peers {
+    /// Checks the peer dependencies of a top level package and everything below it.
+    /// Each peer is resolved against the parent of the package, the package that depends on it or the project for top level packages.
+    /// The project provides everything linked into its node modules folder, `project_packages` maps the names they are linked under to the cached package.
+    /// Optional peers are only checked if the parent provides them.
+    pub fn check(
+        root: &str,
+        dependency_map: &DependencyMap,
+        project_packages: &HashMap<String, String>,
+    ) -> PeerCheck {
+        let mut peer_check = PeerCheck::default();
+
+        // The packages each parent provides, keyed by the name they are linked under
+        let mut provided_packages: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut checked = HashSet::new();
+        let mut pending = vec![(None, Versions::real_package(root))];
+
+        while let Some((parent, package)) = pending.pop() {
+            if !checked.insert((parent.clone(), package.to_string())) {
+                continue;
+            }
+
+            let Some(package_lock) = Self::package_lock(&package, dependency_map) else {
+                continue;
+            };
+
+            let parent_packages = match &parent {
+                Some(parent) => provided_packages.get(parent).unwrap_or(project_packages),
+                None => project_packages,
+            };
+
+            for (peer_name, peer_range) in package_lock.peer_dependencies.iter() {
+                let Some(provided) = parent_packages.get(peer_name) else {
+                    if !package_lock.optional_peers.contains(peer_name) {
+                        peer_check.missing.push(MissingPeer {
+                            parent: parent.clone(),
+                            name: peer_name.to_string(),
+                            range: peer_range.to_string(),
+                        });
+                    }
+                    continue;
+                };
+
+                // A range we can't understand is left for node to sort out, rather than stopping the install
+                let Ok(version_spec) = Versions::parse_dependency_version(peer_range) else {
+                    continue;
+                };
+
+                let (_, provided_version) =
+                    Versions::parse_raw_package_details(provided.to_string());
+                if !version_spec.matches(&provided_version) {
+                    peer_check
+                        .conflicts
+                        .push(CommandError::PeerDependencyConflict(
+                            package.to_string(),
+                            format!("{peer_name}@{peer_range}"),
+                            provided.to_string(),
+                        ));
+                }
+            }
+
+            if provided_packages.contains_key(&package) {
+                continue;
+            }
+
+            let mut dependencies = HashMap::new();
+            for dependency in package_lock.dependencies.iter() {
+                let (link_name, _) = Versions::parse_raw_package_details(dependency.to_string());
+                let dependency = Versions::real_package(dependency);
+
+                pending.push((Some(package.to_string()), dependency.to_string()));
+                dependencies.insert(link_name, dependency);
+            }
+
+            provided_packages.insert(package, dependencies);
+        }
+
+        peer_check
+    }
+
+    /// Packages installed by this install are in the dependency map, anything else has to be read from the cache.
+    fn package_lock(package: &str, dependency_map: &DependencyMap) -> Option<PackageLock> {
+        match dependency_map.get(package) {
+            Some(package_lock) => Some(package_lock.clone()),
+            None => Cache::read_lockfile(package).ok(),
+        }
+    }
+}
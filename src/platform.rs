@@ -0,0 +1,107 @@
+use std::{env, fs};
+
+use lazy_static::lazy_static;
+
+use crate::{errors::CommandError, types::VersionData, versions::Versions};
+
+lazy_static! {
+    // Musl based distros (Alpine) ship musl's dynamic linker instead of glibc's.
+    static ref LIBC: Option<&'static str> = match env::consts::OS {
+        "linux" => {
+            let is_musl = fs::read_dir("/lib").is_ok_and(|entries| {
+                entries.flatten().any(|entry| {
+                    entry.file_name().to_string_lossy().starts_with("ld-musl-")
+                })
+            });
+
+            match is_musl {
+                true => Some("musl"),
+                false => Some("glibc"),
+            }
+        }
+        _ => None,
+    };
+}
+
+pub struct Platform;
+impl Platform {
+    /// The operating system as node names it (process.platform).
+    fn os() -> &'static str {
+        match env::consts::OS {
+            "macos" => "darwin",
+            "windows" => "win32",
+            "solaris" | "illumos" => "sunos",
+            os => os,
+        }
+    }
+
+    /// The cpu architecture as node names it (process.arch).
+    fn cpu() -> &'static str {
+        match env::consts::ARCH {
+            "x86_64" => "x64",
+            "x86" => "ia32",
+            "aarch64" => "arm64",
+            "powerpc" => "ppc",
+            "powerpc64" => "ppc64",
+            "loongarch64" => "loong64",
+            cpu => cpu,
+        }
+    }
+
+    /// Checks the `os`, `cpu` and `libc` fields of a package against this machine, the same way npm does.
+    /// Packages with native binaries use these to publish a package for each platform, usually as optional dependencies of the main package.
+    pub fn check(version_data: &VersionData) -> Result<(), CommandError> {
+        let checks = [
+            ("os", &version_data.os, Some(Self::os())),
+            ("cpu", &version_data.cpu, Some(Self::cpu())),
+            ("libc", &version_data.libc, *LIBC),
+        ];
+
+        for (field, supported, current) in checks {
+            let Some(supported) = supported else {
+                continue;
+            };
+
+            if !Self::is_supported(supported, current) {
+                return Err(CommandError::UnsupportedPlatform(
+                    Versions::stringify(&version_data.name, &version_data.version),
+                    format!(
+                        "{field} is {}, it supports {}",
+                        current.unwrap_or("unknown"),
+                        supported.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Entries starting with `!` exclude a platform, if there are any other entries the platform has to be one of them.
+    /// `current` is None for libc outside of linux, which only a package without any libc requirement supports.
+    fn is_supported(supported: &[String], current: Option<&str>) -> bool {
+        if supported.len() == 1 && supported[0] == "any" {
+            return true;
+        }
+
+        let Some(current) = current else {
+            return false;
+        };
+
+        let mut is_allowed = false;
+        let mut has_allowed = false;
+
+        for entry in supported {
+            match entry.strip_prefix('!') {
+                Some(excluded) if excluded == current => return false,
+                Some(_) => {}
+                None => {
+                    has_allowed = true;
+                    is_allowed |= entry == current;
+                }
+            }
+        }
+
+        is_allowed || !has_allowed
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::{
+    errors::CommandError, http::HTTPRequest, package_json::PackageJson, types::VersionData, util,
+    versions::REMOTE_BUILD_PREFIX,
+};
+
+const REMOTE_PROTOCOLS: &[&str] = &["https://", "http://"];
+
+// Enough of each hash to tell tarballs apart while keeping the cache folder names readable.
+const URL_HASH_LENGTH: usize = 12;
+const INTEGRITY_HASH_LENGTH: usize = 16;
+
+/// A package tarball downloaded straight from a url rather than looked up in the registry.
+#[derive(Clone, Debug)]
+pub struct RemoteSpec {
+    pub url: String,
+}
+
+impl RemoteSpec {
+    /// Parses `https://` and `http://` urls, None is returned if `raw_spec` isn't one.
+    pub fn parse(raw_spec: &str) -> Option<Self> {
+        let is_remote = REMOTE_PROTOCOLS.iter().any(|protocol| {
+            raw_spec
+                .strip_prefix(protocol)
+                .is_some_and(|address| !address.is_empty())
+        });
+
+        match is_remote {
+            true => Some(Self {
+                url: raw_spec.to_string(),
+            }),
+            false => None,
+        }
+    }
+}
+
+impl Display for RemoteSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
+
+pub struct Remote;
+impl Remote {
+    /// Downloads a remote tarball and reads the version data from the package.json inside it.
+    /// The url and the integrity of the tarball are kept in the build metadata (1.0.0+remote.<url>.<integrity>),
+    /// so a different tarball at the same url gets its own cache entry.
+    pub async fn version_data(
+        client: reqwest::Client,
+        remote_spec: &RemoteSpec,
+    ) -> Result<VersionData, CommandError> {
+        let invalid_tarball =
+            |reason: String| CommandError::InvalidTarball(remote_spec.url.to_string(), reason);
+
+        let tarball_bytes = HTTPRequest::get_bytes(client, remote_spec.url.to_string()).await?;
+
+        let package_json_raw = util::read_tarball_file(&tarball_bytes, "package.json")
+            .map_err(|err| invalid_tarball(err.to_string()))?
+            .ok_or(invalid_tarball(String::from("it has no package.json")))?;
+        let package_json = PackageJson::parse(&package_json_raw)?;
+
+        let name = package_json
+            .name
+            .clone()
+            .ok_or(CommandError::MissingPackageName(
+                remote_spec.url.to_string(),
+            ))?;
+
+        let version = package_json
+            .version
+            .clone()
+            .unwrap_or(String::from("0.0.0"));
+        let (version, _) = version.split_once('+').unwrap_or((&version, ""));
+
+        let url_hash = format!("{:x}", Sha256::digest(remote_spec.url.as_bytes()));
+        let integrity = format!("{:x}", Sha512::digest(&tarball_bytes));
+
+        let version = format!(
+            "{version}+{REMOTE_BUILD_PREFIX}{}.{}",
+            &url_hash[..URL_HASH_LENGTH],
+            &integrity[..INTEGRITY_HASH_LENGTH]
+        );
+        let tarball = remote_spec.url.to_string();
+
+        Ok(VersionData {
+            tarball_bytes: Some(tarball_bytes),
+            ..VersionData::from_package_json(package_json, name, version, tarball)
+        })
+    }
+}
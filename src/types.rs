@@ -1,13 +1,100 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
+use crate::{package_json::PackageJson, versions::Versions};
+
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct VersionData {
     pub name: String,
     pub version: String,
     pub dependencies: Option<HashMap<String, String>>,
+    pub optional_dependencies: Option<HashMap<String, String>>,
+    pub peer_dependencies: Option<HashMap<String, String>>,
+    pub peer_dependencies_meta: Option<HashMap<String, PeerDependencyMeta>>,
+    // The platforms the package can be installed on, see Platform::check().
+    pub os: Option<Vec<String>>,
+    pub cpu: Option<Vec<String>>,
+    pub libc: Option<Vec<String>>,
+    // Dependencies the package ships in its own node_modules folder, see bundled_dependencies().
+    #[serde(alias = "bundledDependencies")]
+    pub bundle_dependencies: Option<BundleDependencies>,
     pub dist: Dist,
+    // The folder a local package was read from, the paths of its own local dependencies are relative to it.
+    #[serde(skip)]
+    pub directory: Option<PathBuf>,
+    // Remote tarballs are downloaded to read their package.json, so they are kept rather than downloaded again.
+    #[serde(skip)]
+    pub tarball_bytes: Option<Bytes>,
+}
+
+impl VersionData {
+    /// Version data for a package that doesn't come from the registry, read from its own package.json.
+    pub fn from_package_json(
+        package_json: PackageJson,
+        name: String,
+        version: String,
+        tarball: String,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            dependencies: package_json.dependencies,
+            optional_dependencies: package_json.optional_dependencies,
+            peer_dependencies: package_json.peer_dependencies,
+            peer_dependencies_meta: package_json.peer_dependencies_meta,
+            os: package_json.os,
+            cpu: package_json.cpu,
+            libc: package_json.libc,
+            bundle_dependencies: package_json.bundle_dependencies,
+            dist: Dist { tarball },
+            directory: None,
+            tarball_bytes: None,
+        }
+    }
+
+    /// The peer dependencies that are marked as optional, they are only checked if something else installs them.
+    pub fn optional_peers(&self) -> Vec<String> {
+        self.peer_dependencies_meta
+            .iter()
+            .flatten()
+            .filter(|(_, peer_dependency_meta)| peer_dependency_meta.optional)
+            .map(|(package_name, _)| package_name.to_string())
+            .collect()
+    }
+
+    /// The names of the dependencies that are bundled, `true` bundles every regular and optional dependency.
+    pub fn bundled_dependencies(&self) -> Vec<String> {
+        match &self.bundle_dependencies {
+            Some(BundleDependencies::Names(names)) => names.clone(),
+            Some(BundleDependencies::All(true)) => self
+                .dependencies
+                .iter()
+                .chain(&self.optional_dependencies)
+                .flat_map(|dependencies| dependencies.keys().cloned())
+                .collect(),
+            Some(BundleDependencies::All(false)) | None => Vec::new(),
+        }
+    }
+}
+
+/// The bundleDependencies field is either a list of package names or a boolean.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BundleDependencies {
+    Names(Vec<String>),
+    All(bool),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PeerDependencyMeta {
+    #[serde(default)]
+    pub optional: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,13 +106,39 @@ pub struct Dist {
 #[derive(Deserialize)]
 pub struct PackageData {
     pub versions: HashMap<String, VersionData>,
+    // Tag names (latest, next, beta) mapped to the version they point to.
+    #[serde(rename = "dist-tags", default)]
+    pub dist_tags: HashMap<String, String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PackageLock {
     #[serde(rename = "isLatest")]
     pub is_latest: bool,
     pub dependencies: Vec<String>,
+    // The version range each dependency was requested with, keyed by the dependency's package name.
+    #[serde(default)]
+    pub ranges: HashMap<String, String>,
+    // Peer dependencies are checked against the package's parent on every install, so they are kept for cached packages too.
+    #[serde(
+        rename = "peerDependencies",
+        default,
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub peer_dependencies: HashMap<String, String>,
+    #[serde(
+        rename = "optionalPeers",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub optional_peers: Vec<String>,
+    // The names of the dependencies that are optional, the package is still installed if they fail.
+    #[serde(
+        rename = "optionalDependencies",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub optional_dependencies: Vec<String>,
 }
 
 impl PackageLock {
@@ -33,8 +146,36 @@ impl PackageLock {
         Self {
             is_latest,
             dependencies: Vec::new(),
+            ranges: HashMap::new(),
+            peer_dependencies: HashMap::new(),
+            optional_peers: Vec::new(),
+            optional_dependencies: Vec::new(),
         }
     }
+
+    /// Whether a dependency (package@version, or alias@npm:package@version) is one of the optional dependencies.
+    pub fn is_optional(&self, dependency: &str) -> bool {
+        let (package_name, _) = Versions::parse_raw_package_details(dependency.to_string());
+        self.optional_dependencies.contains(&package_name)
+    }
 }
 
 pub type DependencyMap = HashMap<String, PackageLock>;
+
+/// Kept in the node modules folder of a project, the package locks in the cache are shared between projects so they can't say why a package is linked into this one.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ProjectLock {
+    // Linked packages that only devDependencies need, `click prune --production` removes them.
+    #[serde(rename = "devOnly", default)]
+    pub dev_only: Vec<String>,
+    // Peer dependencies that were installed automatically, they aren't in the package.json so this is what keeps them linked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peers: Vec<String>,
+    // The locks of packages that overrides replaced dependencies of, they are used instead of the ones in the cache.
+    #[serde(
+        rename = "overriddenLocks",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub overridden_locks: BTreeMap<String, PackageLock>,
+}
@@ -1,5 +1,9 @@
 use std::{
+    collections::HashSet,
+    fs,
     future::Future,
+    io::{self, Read},
+    path::Path,
     sync::atomic::{self, AtomicUsize},
     thread::{self},
     time::Duration,
@@ -7,8 +11,8 @@ use std::{
 
 use atomic::Ordering::SeqCst;
 use bytes::Bytes;
-use flate2::bufread::GzDecoder;
-use tar::Archive;
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use tar::{Archive, Builder};
 use tokio::task::JoinHandle;
 
 use crate::errors::CommandError;
@@ -24,6 +28,81 @@ pub fn extract_tarball(bytes: Bytes, dest: String) -> Result<(), CommandError> {
         .map_err(CommandError::ExtractionFailed)
 }
 
+/// Reads a file from a package tarball without extracting it, the path is relative to the tarball's top level directory (usually `package`).
+pub fn read_tarball_file(bytes: &Bytes, path: &str) -> Result<Option<String>, io::Error> {
+    let mut archive = Archive::new(GzDecoder::new(&bytes[..]));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().to_string();
+
+        let is_match = entry_path
+            .split_once('/')
+            .is_some_and(|(_, entry_path)| entry_path == path);
+
+        if is_match {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The bundled dependencies that a package tarball really contains, in a node_modules folder next to its package.json.
+/// Git and local packages are packed without their node_modules, so they can list bundled dependencies that aren't there.
+pub fn bundled_packages(bytes: &Bytes, bundled: &[String]) -> Result<HashSet<String>, io::Error> {
+    let mut archive = Archive::new(GzDecoder::new(&bytes[..]));
+    let mut bundled_packages = HashSet::new();
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().to_string();
+
+        let Some((_, entry_path)) = entry_path.split_once('/') else {
+            continue;
+        };
+
+        let bundled_package = entry_path
+            .strip_prefix("node_modules/")
+            .and_then(|entry_path| entry_path.strip_suffix("/package.json"))
+            .filter(|package_name| bundled.iter().any(|bundled| bundled == package_name));
+
+        if let Some(package_name) = bundled_package {
+            bundled_packages.insert(package_name.to_string());
+        }
+    }
+
+    Ok(bundled_packages)
+}
+
+// These never end up in a packed package.
+const IGNORED_ENTRIES: &[&str] = &[".git", "node_modules"];
+
+/// Packs a folder into a gzipped tarball with everything inside of a `package` directory, the same layout as the registry's tarballs.
+pub fn pack_directory(directory: &Path) -> Result<Bytes, io::Error> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if IGNORED_ENTRIES.contains(&file_name.as_str()) {
+            continue;
+        }
+
+        let archive_path = format!("package/{file_name}");
+
+        match entry.path().is_dir() {
+            true => builder.append_dir_all(archive_path, entry.path())?,
+            false => builder.append_path_with_name(entry.path(), archive_path)?,
+        }
+    }
+
+    Ok(Bytes::from(builder.into_inner()?.finish()?))
+}
+
 pub static ACTIVE_TASKS: AtomicUsize = AtomicUsize::new(0);
 
 pub struct TaskAllocator;
@@ -34,8 +113,10 @@ impl TaskAllocator {
         T: Future + Send + 'static,
         T::Output: Send + 'static,
     {
+        // The task is counted before it is spawned, otherwise block_until_done() could return before it starts
+        Self::increment_tasks();
+
         tokio::spawn(async move {
-            Self::increment_tasks();
             let future_result = future.await;
             Self::decrement_tasks();
 
@@ -48,8 +129,9 @@ impl TaskAllocator {
         F: FnOnce() -> R + Send + 'static,
         R: Send + 'static,
     {
+        Self::increment_tasks();
+
         tokio::task::spawn_blocking(move || {
-            Self::increment_tasks();
             let task_result = f();
             Self::decrement_tasks();
 
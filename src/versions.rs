@@ -1,122 +1,211 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{cmp::Ordering, collections::HashMap, fmt::Display, str::FromStr};
 
-use semver::{BuildMetadata, Comparator, Op, Prerelease, Version, VersionReq};
+use semver::{BuildMetadata, Prerelease, Version};
 
 use crate::{
     errors::{CommandError, ParseError},
-    types::VersionData,
-};
-
-pub const EMPTY_VERSION: Version = Version {
-    major: 0,
-    minor: 0,
-    patch: 0,
-    pre: Prerelease::EMPTY,
-    build: BuildMetadata::EMPTY,
+    git::GitSpec,
+    local::LocalSpec,
+    logger::Logger,
+    remote::RemoteSpec,
+    types::{PackageData, VersionData},
 };
 
 pub const LATEST: &str = "latest";
+pub const ALIAS_PREFIX: &str = "npm:";
 
-type PackageDetails = (String, Option<Comparator>);
+// Packages that don't come from the registry are cached with where they came from in the build metadata (1.0.0+git.<commit>).
+pub const GIT_BUILD_PREFIX: &str = "git.";
+pub const FILE_BUILD_PREFIX: &str = "file.";
+pub const LINK_BUILD_PREFIX: &str = "link.";
+pub const REMOTE_BUILD_PREFIX: &str = "remote.";
+
+pub type PackageDetails = (String, VersionSpec);
 
 pub struct Versions;
 impl Versions {
+    /// Splits package@version into the name and version, scoped names keep their leading `@` (@types/node@20).
     pub fn parse_raw_package_details(details: String) -> (String, String) {
-        let mut split = details.split('@');
+        // The first character is skipped so the `@` of a scope isn't mistaken for the start of the version
+        let version_start = details
+            .char_indices()
+            .skip(1)
+            .find(|(_, char)| *char == '@')
+            .map(|(index, _)| index);
+
+        match version_start {
+            Some(index) => (
+                details[..index].to_string(),
+                details[index + 1..].to_string(),
+            ),
+            None => (details, LATEST.to_string()),
+        }
+    }
+
+    pub fn parse_semantic_version(raw_version: &str) -> Result<VersionRange, ParseError> {
+        VersionRange::from_str(raw_version)
+    }
+
+    /// Parses a version as it appears after the package name, anything that isn't a valid range is treated as a dist-tag (latest, next, beta).
+    /// `npm:real-name@range` installs `real-name` in place of the package it is listed under.
+    /// Git repositories (github:user/repo#ref or git+https://...) are installed from the repository instead of the registry,
+    /// `file:path` or `link:path` from a folder on this machine and `https://` urls from a tarball.
+    pub fn parse_dependency_version(raw_version: &str) -> Result<VersionSpec, ParseError> {
+        if let Some(aliased_package) = raw_version.strip_prefix(ALIAS_PREFIX) {
+            return Self::parse_alias(raw_version, aliased_package);
+        }
+
+        if let Some(local_spec) = LocalSpec::parse(raw_version) {
+            return Ok(VersionSpec::Local(local_spec));
+        }
+
+        if let Some(remote_spec) = RemoteSpec::parse(raw_version) {
+            return Ok(VersionSpec::Remote(remote_spec));
+        }
 
-        let name = split
-            .next()
-            .expect("Provided package name is empty")
-            .to_string();
+        if let Some(git_spec) = GitSpec::parse(raw_version) {
+            return Ok(VersionSpec::Git(git_spec));
+        }
+
+        match Self::parse_semantic_version(raw_version) {
+            Ok(semantic_version) => Ok(VersionSpec::Range(semantic_version)),
+            Err(_) if Self::is_tag(raw_version) => Ok(VersionSpec::Tag(raw_version.to_string())),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn parse_alias(raw_version: &str, aliased_package: &str) -> Result<VersionSpec, ParseError> {
+        let (real_name, real_version) =
+            Self::parse_raw_package_details(aliased_package.to_string());
+
+        if real_name.is_empty() {
+            return Err(ParseError::InvalidAlias(
+                raw_version.to_string(),
+                String::from("missing package name"),
+            ));
+        }
 
-        match split.next() {
-            Some(version_raw) => (name, version_raw.to_string()),
-            None => (name, LATEST.to_string()),
+        match Self::parse_dependency_version(&real_version)? {
+            VersionSpec::Alias(_, _) => Err(ParseError::InvalidAlias(
+                raw_version.to_string(),
+                String::from("an alias can't point to another alias"),
+            )),
+            version_spec if !version_spec.is_registry() => Err(ParseError::InvalidAlias(
+                raw_version.to_string(),
+                String::from("an alias has to point to a registry package"),
+            )),
+            version_spec => Ok(VersionSpec::Alias(real_name, Box::new(version_spec))),
         }
     }
 
-    pub fn parse_semantic_version(raw_version: &str) -> Result<Comparator, ParseError> {
-        let mut version =
-            VersionReq::parse(raw_version).map_err(ParseError::InvalidVersionNotation)?;
-        Ok(version.comparators.remove(0))
+    /// Tags are used in the registry's urls, so they are limited to url safe characters.
+    fn is_tag(raw_version: &str) -> bool {
+        !raw_version.is_empty()
+            && raw_version
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || matches!(char, '-' | '.' | '_' | '~'))
     }
 
+    /// The name of a git, local or remote package isn't known until its package.json has been read, so it is left empty.
     pub fn parse_semantic_package_details(details: String) -> Result<PackageDetails, ParseError> {
-        let (name, version_raw) = Self::parse_raw_package_details(details);
+        if let Some(local_spec) = LocalSpec::parse(&details) {
+            return Ok((String::new(), VersionSpec::Local(local_spec)));
+        }
 
-        if version_raw == LATEST {
-            return Ok((name, None));
+        if let Some(remote_spec) = RemoteSpec::parse(&details) {
+            return Ok((String::new(), VersionSpec::Remote(remote_spec)));
         }
 
-        let comparator = Self::parse_semantic_version(&version_raw)?;
-        Ok((name, Some(comparator)))
+        if let Some(git_spec) = GitSpec::parse(&details) {
+            return Ok((String::new(), VersionSpec::Git(git_spec)));
+        }
+
+        let (name, version_raw) = Self::parse_raw_package_details(details);
+        let semantic_version = Self::parse_dependency_version(&version_raw)?;
+
+        Ok((name, semantic_version))
     }
 
-    /// If a version comparator has the major, patch and minor available a string version will be returned with the resolved version.
+    /// If a version range is a single comparator with the major, patch and minor available a string version will be returned with the resolved version.
+    /// `latest` is returned as it is since the registry resolves it itself, other tags are looked up in the dist-tags of the full package data.
     /// This version string can be used to retrieve a package version from the NPM registry.
     /// If the version is not resolvable without requesting the full package data, None will be returned.
-    /// None will also be returned if the version operator is Op::Less (<?.?.?) because we need all versions to get the latest version less than this
-    pub fn resolve_full_version(semantic_version: Option<&Comparator>) -> Option<String> {
+    /// None will also be returned if the version operator is Operator::Less (<?.?.?) because we need all versions to get the latest version less than this,
+    /// and for ranges with more than one comparator (>=1.2.0 <2.0.0 or ^1.0.0 || ^2.0.0) as every comparator has to be checked against the available versions.
+    /// `latest` is also returned for `>` and `>=`, so get_version_data() has to check it actually satisfies the range.
+    pub fn resolve_full_version(version_spec: &VersionSpec) -> Option<String> {
         let latest = LATEST.to_string();
 
-        let semantic_version = match semantic_version {
-            Some(semantic_version) => semantic_version,
-            None => return Some(latest),
+        let semantic_version = match version_spec {
+            VersionSpec::Alias(_, version_spec) => return Self::resolve_full_version(version_spec),
+            VersionSpec::Tag(tag) if tag == LATEST => return Some(latest),
+            VersionSpec::Tag(_)
+            | VersionSpec::Git(_)
+            | VersionSpec::Local(_)
+            | VersionSpec::Remote(_) => return None,
+            VersionSpec::Range(semantic_version) if semantic_version.is_any() => {
+                return Some(latest)
+            }
+            VersionSpec::Range(semantic_version) => semantic_version,
         };
 
-        let (minor, patch) = match (semantic_version.minor, semantic_version.patch) {
-            (Some(minor), Some(patch)) => (minor, patch),
+        let (operator, version) = match semantic_version.single_comparator() {
+            Some(RangeComparator::Single(operator, version)) => (operator, version),
             _ => return None,
         };
 
-        match semantic_version.op {
-            Op::Greater | Op::GreaterEq | Op::Wildcard => Some(latest),
-            Op::Exact | Op::LessEq | Op::Tilde | Op::Caret => Some(Self::stringify_from_numbers(
-                semantic_version.major,
-                minor,
-                patch,
-            )),
+        let full_version = version.to_full_version()?;
+
+        match operator {
+            Operator::Greater | Operator::GreaterEq => Some(latest),
+            Operator::Exact => Some(full_version.to_string()),
+            // The prerelease a range mentions is rarely published under that exact name (^1.0.0-beta with 1.0.0-beta.1)
+            Operator::LessEq | Operator::Tilde | Operator::Caret if full_version.pre.is_empty() => {
+                Some(full_version.to_string())
+            }
             _ => None,
         }
     }
 
-    /// Should only be executed if the version comparator is missing a minor or patch.
-    /// This can be checked with resolve_full_version() which will return None if this is the case.
+    /// Picks a version out of the full package data, tags are looked up in the dist-tags.
+    /// Like npm, the version tagged as latest is preferred if it satisfies the range, even if there are higher versions.
+    /// This stops a prerelease-only major (or a release published to an older major) from being picked over what the maintainers marked as latest.
     pub fn resolve_partial_version(
-        semantic_version: Option<&Comparator>,
-        available_versions: &HashMap<String, VersionData>,
+        version_spec: &VersionSpec,
+        package_data: &PackageData,
     ) -> Result<String, CommandError> {
-        let semantic_version = semantic_version
-            .expect("Function should not be called as the version can be resolved to 'latest'");
+        let semantic_version = match version_spec {
+            VersionSpec::Alias(_, version_spec) => {
+                return Self::resolve_partial_version(version_spec, package_data)
+            }
+            // The registry has no say over what a repository or folder contains
+            VersionSpec::Git(_) | VersionSpec::Local(_) | VersionSpec::Remote(_) => {
+                return Err(CommandError::InvalidVersion)
+            }
+            VersionSpec::Tag(tag) => {
+                return package_data
+                    .dist_tags
+                    .get(tag)
+                    .cloned()
+                    .ok_or(CommandError::TagNotFound(tag.to_string()))
+            }
+            VersionSpec::Range(semantic_version) => semantic_version,
+        };
 
-        let mut versions = available_versions.iter().collect::<Vec<_>>();
+        if let Some(latest_version) = package_data.dist_tags.get(LATEST) {
+            let is_available = package_data.versions.contains_key(latest_version);
 
-        // Serde scambles the order of the hashmap so we need to reorder it to find the latest versions
-        Self::sort(&mut versions);
-
-        if semantic_version.op == Op::Less {
-            // Annoyingly we can't put `if let` and other comparisons on the same line as it's unstable as of writing
-            if let (Some(minor), Some(patch)) = (semantic_version.minor, semantic_version.patch) {
-                let version_position = versions
-                    .iter()
-                    .position(|(ver, _)| {
-                        ver == &&Self::stringify_from_numbers(semantic_version.major, minor, patch)
-                    })
-                    .ok_or(CommandError::InvalidVersion)?;
-
-                return Ok(versions
-                    .get(version_position - 1)
-                    .expect("Invalid version provided (no smaller versions available)")
-                    .0
-                    .to_string());
+            if is_available && version_spec.matches(latest_version) {
+                return Ok(latest_version.to_string());
             }
         }
 
-        // Do in reverse order so we find the latest compatible version.
-        for (version_str, _) in versions.iter().rev() {
-            let version = Version::from_str(version_str.as_str()).unwrap_or(EMPTY_VERSION);
+        // Serde scambles the order of the hashmap so we need to reorder it to find the latest versions
+        let versions = Self::sort(&package_data.versions);
 
-            if semantic_version.matches(&version) {
+        // Do in reverse order so we find the latest compatible version, this also covers `<` as every comparator must match.
+        for (version, version_str) in versions.iter().rev() {
+            if semantic_version.matches(version) {
                 return Ok(version_str.to_string());
             }
         }
@@ -128,6 +217,58 @@ impl Versions {
         format!("{}@{}", name, version)
     }
 
+    /// The stringified version a package is linked as, packages linked under a name other than their own (aliases, or git packages listed under another name)
+    /// are formatted as alias@npm:package@version (lodash4@npm:lodash@4.17.21).
+    /// `stringified` is the package that was actually installed.
+    pub fn stringify_alias(alias: &str, stringified: &str) -> String {
+        let (package_name, _) = Self::parse_raw_package_details(stringified.to_string());
+
+        match package_name == alias {
+            true => stringified.to_string(),
+            false => format!("{alias}@{ALIAS_PREFIX}{stringified}"),
+        }
+    }
+
+    /// Strips the alias from a stringified package, leaving the package@version that is in the cache.
+    pub fn real_package(package: &str) -> String {
+        let (_, version) = Self::parse_raw_package_details(package.to_string());
+
+        match version.strip_prefix(ALIAS_PREFIX) {
+            Some(real_package) => real_package.to_string(),
+            None => package.to_string(),
+        }
+    }
+
+    /// Checks the build metadata of a version for where a package that isn't from the registry came from.
+    fn has_build_prefix(version: &str, build_prefixes: &[&str]) -> bool {
+        version.split_once('+').is_some_and(|(_, build)| {
+            build_prefixes
+                .iter()
+                .any(|build_prefix| build.starts_with(build_prefix))
+        })
+    }
+
+    /// Packages installed from git or a local folder aren't the same as the registry version they are named after.
+    pub fn is_registry_version(version: &str) -> bool {
+        !Self::has_build_prefix(
+            version,
+            &[
+                GIT_BUILD_PREFIX,
+                FILE_BUILD_PREFIX,
+                LINK_BUILD_PREFIX,
+                REMOTE_BUILD_PREFIX,
+            ],
+        )
+    }
+
+    pub fn is_local_version(version: &str) -> bool {
+        Self::has_build_prefix(version, &[FILE_BUILD_PREFIX, LINK_BUILD_PREFIX])
+    }
+
+    pub fn is_link_version(version: &str) -> bool {
+        Self::has_build_prefix(version, &[LINK_BUILD_PREFIX])
+    }
+
     /// Takes in a result of Versions::resolve_full_version()
     pub fn is_latest(version_string: Option<&String>) -> bool {
         match version_string {
@@ -136,12 +277,684 @@ impl Versions {
         }
     }
 
-    // This might not be effective for versions that include a prerelease in the version (experimental, canary etc)
-    fn sort(versions_vec: &mut [(&String, &VersionData)]) {
-        versions_vec.sort_by(|a, b| a.0.cmp(b.0))
+    /// Compares versions by semver precedence, unlike `Ord` on `Version` the build metadata is ignored.
+    pub fn compare(a: &Version, b: &Version) -> Ordering {
+        (a.major, a.minor, a.patch, &a.pre).cmp(&(b.major, b.minor, b.patch, &b.pre))
     }
 
-    pub fn stringify_from_numbers(major: u64, minor: u64, patch: u64) -> String {
-        format!("{}.{}.{}", major, minor, patch)
+    /// Orders the versions by semver precedence (1.9.0 < 1.10.0 and 1.0.0-beta < 1.0.0).
+    /// Versions that aren't valid semver are reported and left out, as there is no way to tell where they belong.
+    fn sort(available_versions: &HashMap<String, VersionData>) -> Vec<(Version, &String)> {
+        let mut versions = available_versions
+            .iter()
+            .filter_map(
+                |(version_str, version_data)| match Version::from_str(version_str) {
+                    Ok(version) => Some((version, version_str)),
+                    Err(err) => {
+                        Logger::warn(format!(
+                            "Ignoring version '{version_str}' of '{}' as it is not valid semver ({err})",
+                            version_data.name
+                        ));
+                        None
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        versions.sort_by(|a, b| Self::compare(&a.0, &b.0));
+        versions
+    }
+}
+
+/// What a package was asked for with, either a dist-tag or a version range.
+/// An alias is another package (and what it was asked for with) that gets installed under the name it is listed as.
+/// Git, local and remote specs are a repository, folder or tarball that gets installed instead of anything from the registry.
+#[derive(Clone, Debug)]
+pub enum VersionSpec {
+    Tag(String),
+    Range(VersionRange),
+    Alias(String, Box<VersionSpec>),
+    Git(GitSpec),
+    Local(LocalSpec),
+    Remote(RemoteSpec),
+}
+
+impl VersionSpec {
+    /// Checks a version against the range, every version satisfies a tag (or git spec) as it has already been resolved.
+    pub fn matches(&self, version: &str) -> bool {
+        match self {
+            VersionSpec::Tag(_)
+            | VersionSpec::Git(_)
+            | VersionSpec::Local(_)
+            | VersionSpec::Remote(_) => true,
+            VersionSpec::Range(semantic_version) => {
+                Version::from_str(version).is_ok_and(|version| semantic_version.matches(&version))
+            }
+            VersionSpec::Alias(_, version_spec) => version_spec.matches(version),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        match self {
+            VersionSpec::Range(semantic_version) => semantic_version.is_exact(),
+            VersionSpec::Alias(_, version_spec) => version_spec.is_exact(),
+            VersionSpec::Tag(_)
+            | VersionSpec::Git(_)
+            | VersionSpec::Local(_)
+            | VersionSpec::Remote(_) => false,
+        }
+    }
+
+    pub fn is_registry(&self) -> bool {
+        !matches!(
+            self,
+            VersionSpec::Git(_) | VersionSpec::Local(_) | VersionSpec::Remote(_)
+        )
+    }
+
+    /// The package that actually gets installed for `package_name` and what it was asked for with.
+    pub fn real_package<'a>(&'a self, package_name: &'a str) -> (&'a str, &'a VersionSpec) {
+        match self {
+            VersionSpec::Alias(real_name, version_spec) => (real_name, version_spec),
+            _ => (package_name, self),
+        }
+    }
+
+    /// What has to come before a version for it to point at the same package, `npm:package@` for aliases.
+    pub fn alias_prefix(&self) -> String {
+        match self {
+            VersionSpec::Alias(real_name, _) => format!("{ALIAS_PREFIX}{real_name}@"),
+            _ => String::new(),
+        }
+    }
+}
+
+impl Default for VersionSpec {
+    fn default() -> Self {
+        VersionSpec::Tag(LATEST.to_string())
+    }
+}
+
+impl Display for VersionSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionSpec::Tag(tag) => write!(f, "{tag}"),
+            VersionSpec::Range(semantic_version) => write!(f, "{semantic_version}"),
+            VersionSpec::Alias(real_name, version_spec) => {
+                write!(f, "{ALIAS_PREFIX}{real_name}@{version_spec}")
+            }
+            VersionSpec::Git(git_spec) => write!(f, "{git_spec}"),
+            VersionSpec::Local(local_spec) => write!(f, "{local_spec}"),
+            VersionSpec::Remote(remote_spec) => write!(f, "{remote_spec}"),
+        }
+    }
+}
+
+/// An npm version range, made up of comparator sets separated by `||`.
+/// A version satisfies the range if it satisfies every comparator in at least one of the sets.
+#[derive(Clone, Debug)]
+pub struct VersionRange {
+    raw: String,
+    sets: Vec<Vec<RangeComparator>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+/// A comparator as it was written, before it is turned into primitive comparators for matching.
+#[derive(Clone, Debug)]
+pub enum RangeComparator {
+    Single(Operator, PartialVersion),
+    Hyphen(PartialVersion, PartialVersion),
+}
+
+/// A version which can be missing its minor and patch, or have them as wildcards (1, 1.2, 1.x, 1.2.*).
+#[derive(Clone, Debug)]
+pub struct PartialVersion {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Prerelease,
+}
+
+// Comparators are reduced to these before matching, in the same way node-semver desugars its ranges
+type PrimitiveComparator = (Operator, Version);
+
+impl VersionRange {
+    /// True for ranges which allow any version (*, x or an empty string).
+    pub fn is_any(&self) -> bool {
+        self.sets.iter().any(|set| set.is_empty())
+    }
+
+    /// The only comparator in the range, if it has exactly one.
+    pub fn single_comparator(&self) -> Option<&RangeComparator> {
+        match self.sets.as_slice() {
+            [set] => match set.as_slice() {
+                [comparator] => Some(comparator),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// True if the range only allows a single version (1.2.3 or =1.2.3).
+    pub fn is_exact(&self) -> bool {
+        matches!(
+            self.single_comparator(),
+            Some(RangeComparator::Single(Operator::Exact, version)) if version.to_full_version().is_some()
+        )
+    }
+
+    /// Prereleases follow npm's rule, they only match a comparator set which mentions a prerelease of the same major, minor and patch.
+    /// This is so `^1.0.0` doesn't pull in `1.5.0-beta` but `^1.5.0-beta` does allow `1.5.0-rc`.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.sets.iter().any(|set| {
+            let primitives = set
+                .iter()
+                .flat_map(|comparator| comparator.to_primitives())
+                .collect::<Vec<_>>();
+
+            let is_allowed_prerelease = version.pre.is_empty()
+                || primitives.iter().any(|(_, bound)| {
+                    !bound.pre.is_empty()
+                        && (bound.major, bound.minor, bound.patch)
+                            == (version.major, version.minor, version.patch)
+                });
+
+            is_allowed_prerelease
+                && primitives.iter().all(|(operator, bound)| match operator {
+                    Operator::Greater => Versions::compare(version, bound).is_gt(),
+                    Operator::GreaterEq => Versions::compare(version, bound).is_ge(),
+                    Operator::Less => Versions::compare(version, bound).is_lt(),
+                    Operator::LessEq => Versions::compare(version, bound).is_le(),
+                    _ => Versions::compare(version, bound).is_eq(),
+                })
+        })
+    }
+
+    /// Parses a single comparator set, comparators are separated by whitespace and an operator may be separated from its version (< 2.2.3 > 1.1.2).
+    fn parse_set(raw_set: &str) -> Result<Vec<RangeComparator>, String> {
+        let parts = raw_set.split_whitespace().collect::<Vec<_>>();
+
+        if let [lower, "-", upper] = parts.as_slice() {
+            return Ok(vec![RangeComparator::Hyphen(
+                PartialVersion::parse(lower)?,
+                PartialVersion::parse(upper)?,
+            )]);
+        }
+
+        let mut raw_comparators: Vec<String> = Vec::new();
+
+        for part in parts {
+            match raw_comparators.last_mut() {
+                Some(previous) if previous.chars().all(|char| "<>=~^".contains(char)) => {
+                    previous.push_str(part)
+                }
+                _ => raw_comparators.push(part.to_string()),
+            }
+        }
+
+        let mut comparators = Vec::new();
+
+        for raw_comparator in raw_comparators {
+            let comparator = RangeComparator::parse(&raw_comparator)?;
+
+            // Wildcards are dropped as they allow anything, an empty set is how "any version" is represented
+            if !comparator.is_wildcard() {
+                comparators.push(comparator);
+            }
+        }
+
+        Ok(comparators)
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = ParseError;
+
+    fn from_str(raw_range: &str) -> Result<Self, Self::Err> {
+        let raw = raw_range.trim().to_string();
+        let sets = raw
+            .split("||")
+            .map(Self::parse_set)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|reason| ParseError::InvalidVersionRange(raw.to_string(), reason))?;
+
+        Ok(Self { raw, sets })
+    }
+}
+
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.raw.is_empty() {
+            true => write!(f, "*"),
+            false => write!(f, "{}", self.raw),
+        }
+    }
+}
+
+impl RangeComparator {
+    fn parse(raw_comparator: &str) -> Result<Self, String> {
+        // Longer operators come first so `>=` isn't read as `>`
+        let operators = [
+            (">=", Operator::GreaterEq),
+            ("<=", Operator::LessEq),
+            ("~>", Operator::Tilde),
+            (">", Operator::Greater),
+            ("<", Operator::Less),
+            ("=", Operator::Exact),
+            ("~", Operator::Tilde),
+            ("^", Operator::Caret),
+        ];
+
+        let (operator, raw_version) = operators
+            .iter()
+            .find_map(|(prefix, operator)| {
+                raw_comparator
+                    .strip_prefix(prefix)
+                    .map(|raw_version| (*operator, raw_version))
+            })
+            .unwrap_or((Operator::Exact, raw_comparator));
+
+        Ok(Self::Single(operator, PartialVersion::parse(raw_version)?))
+    }
+
+    fn is_wildcard(&self) -> bool {
+        match self {
+            Self::Single(Operator::Greater | Operator::Less, _) => false,
+            Self::Single(_, version) => version.major.is_none(),
+            Self::Hyphen(lower, upper) => lower.major.is_none() && upper.major.is_none(),
+        }
+    }
+
+    /// Desugars the comparator into primitive comparators, following node-semver's rules for each operator.
+    /// Exclusive upper bounds use the `-0` prerelease so prereleases of the bound aren't let in, e.g. `^1.2.3` is `>=1.2.3 <2.0.0-0`.
+    fn to_primitives(&self) -> Vec<PrimitiveComparator> {
+        let (operator, version) = match self {
+            Self::Hyphen(lower, upper) => {
+                let mut primitives = Vec::new();
+
+                if lower.major.is_some() {
+                    primitives.push((Operator::GreaterEq, lower.lower_bound()));
+                }
+
+                match (upper.major, upper.to_full_version()) {
+                    (None, _) => (),
+                    (Some(_), Some(upper)) => primitives.push((Operator::LessEq, upper)),
+                    (Some(_), None) => primitives.push((Operator::Less, upper.next_bound())),
+                }
+
+                return primitives;
+            }
+            Self::Single(operator, version) => (*operator, version),
+        };
+
+        let full_version = version.to_full_version();
+
+        // A missing major is a wildcard, which either matches everything or (for > and <) nothing
+        if version.major.is_none() {
+            return match operator {
+                Operator::Greater | Operator::Less => {
+                    vec![(Operator::Less, PartialVersion::zero_prerelease(0, 0, 0))]
+                }
+                _ => Vec::new(),
+            };
+        }
+
+        match (operator, full_version) {
+            (Operator::Exact, Some(version)) => vec![(Operator::Exact, version)],
+            (Operator::Greater, Some(version)) => vec![(Operator::Greater, version)],
+            (Operator::Less, Some(version)) => vec![(Operator::Less, version)],
+            (Operator::LessEq, Some(version)) => vec![(Operator::LessEq, version)],
+            (Operator::GreaterEq, _) => vec![(Operator::GreaterEq, version.lower_bound())],
+            (Operator::Greater, None) => vec![(Operator::GreaterEq, version.next_bound_release())],
+            (Operator::Less, None) => vec![(Operator::Less, version.lower_bound_prerelease())],
+            (Operator::LessEq, None) => vec![(Operator::Less, version.next_bound())],
+            (Operator::Exact, None) => vec![
+                (Operator::GreaterEq, version.lower_bound()),
+                (Operator::Less, version.next_bound()),
+            ],
+            (Operator::Tilde, _) => vec![
+                (Operator::GreaterEq, version.lower_bound()),
+                (Operator::Less, version.tilde_bound()),
+            ],
+            (Operator::Caret, _) => vec![
+                (Operator::GreaterEq, version.lower_bound()),
+                (Operator::Less, version.caret_bound()),
+            ],
+        }
+    }
+}
+
+impl PartialVersion {
+    /// Parses a version loosely, a leading `v` or `=` is allowed and the minor and patch can be left out or be x, X or *.
+    fn parse(raw_version: &str) -> Result<Self, String> {
+        let raw_version = raw_version.trim().trim_start_matches(['=', 'v', 'V']);
+
+        // Build metadata has no effect on precedence
+        let raw_version = raw_version.split('+').next().unwrap_or_default();
+
+        let (raw_numbers, raw_pre) = match raw_version.split_once('-') {
+            Some((raw_numbers, raw_pre)) => (raw_numbers, Some(raw_pre)),
+            None => (raw_version, None),
+        };
+
+        if raw_numbers.is_empty() {
+            return Ok(Self::any());
+        }
+
+        let mut numbers = [None; 3];
+        let mut parts = raw_numbers.split('.');
+
+        for number in numbers.iter_mut() {
+            let Some(part) = parts.next() else {
+                break;
+            };
+
+            *number = match part {
+                "x" | "X" | "*" => None,
+                _ => Some(
+                    part.parse::<u64>()
+                        .map_err(|_| format!("'{part}' is not a version number"))?,
+                ),
+            };
+        }
+
+        if parts.next().is_some() {
+            return Err(format!("'{raw_numbers}' has too many version numbers"));
+        }
+
+        // Anything after a wildcard is a wildcard too, 1.x.3 is the same as 1.x
+        let [major, minor, patch] = numbers;
+        let minor = major.and(minor);
+        let patch = minor.and(patch);
+
+        let pre = match (raw_pre, patch) {
+            (Some(raw_pre), Some(_)) => Prerelease::new(raw_pre)
+                .map_err(|_| format!("'{raw_pre}' is not a valid prerelease"))?,
+            (Some(raw_pre), None) => {
+                return Err(format!(
+                    "'{raw_pre}' can only be used on a version with a major, minor and patch"
+                ))
+            }
+            (None, _) => Prerelease::EMPTY,
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    fn any() -> Self {
+        Self {
+            major: None,
+            minor: None,
+            patch: None,
+            pre: Prerelease::EMPTY,
+        }
+    }
+
+    /// The version if all of the major, minor and patch are present.
+    pub fn to_full_version(&self) -> Option<Version> {
+        Some(Version {
+            major: self.major?,
+            minor: self.minor?,
+            patch: self.patch?,
+            pre: self.pre.clone(),
+            build: BuildMetadata::EMPTY,
+        })
+    }
+
+    /// The smallest version this matches, with missing numbers as 0.
+    fn lower_bound(&self) -> Version {
+        self.to_full_version().unwrap_or(Version::new(
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+        ))
+    }
+
+    /// Same as lower_bound() but with the `-0` prerelease, so nothing of this version is matched by `<`.
+    fn lower_bound_prerelease(&self) -> Version {
+        Self::zero_prerelease(
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+        )
+    }
+
+    /// The first release after every version this partial version covers, 1.2 is followed by 1.3.0.
+    fn next_bound_release(&self) -> Version {
+        let bound = self.next_bound();
+        Version::new(bound.major, bound.minor, bound.patch)
+    }
+
+    /// The exclusive upper bound of a partial version, `<1.3.0-0` for 1.2.
+    fn next_bound(&self) -> Version {
+        let major = self.major.unwrap_or(0);
+
+        match (self.minor, self.patch) {
+            (None, _) => Self::zero_prerelease(major + 1, 0, 0),
+            (Some(minor), None) => Self::zero_prerelease(major, minor + 1, 0),
+            (Some(minor), Some(patch)) => Self::zero_prerelease(major, minor, patch + 1),
+        }
+    }
+
+    /// ~1.2.3 allows patch changes, ~1 allows minor changes.
+    fn tilde_bound(&self) -> Version {
+        let major = self.major.unwrap_or(0);
+
+        match self.minor {
+            Some(minor) => Self::zero_prerelease(major, minor + 1, 0),
+            None => Self::zero_prerelease(major + 1, 0, 0),
+        }
+    }
+
+    /// ^ allows changes that don't modify the left-most non-zero number.
+    fn caret_bound(&self) -> Version {
+        let major = self.major.unwrap_or(0);
+
+        match (major, self.minor, self.patch) {
+            (0, Some(0), Some(patch)) => Self::zero_prerelease(0, 0, patch + 1),
+            (0, Some(minor), _) => Self::zero_prerelease(0, minor + 1, 0),
+            _ => Self::zero_prerelease(major + 1, 0, 0),
+        }
+    }
+
+    fn zero_prerelease(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: Prerelease::new("0").unwrap(),
+            build: BuildMetadata::EMPTY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks each range against the versions it should and shouldn't match.
+    fn check_ranges(cases: &[(&str, &[&str], &[&str])]) {
+        for (raw_range, matching, not_matching) in cases {
+            let version_range = VersionRange::from_str(raw_range)
+                .unwrap_or_else(|err| panic!("'{raw_range}' failed to parse: {err}"));
+
+            for (versions, expected) in [(matching, true), (not_matching, false)] {
+                for version in versions.iter() {
+                    assert_eq!(
+                        version_range.matches(&Version::parse(version).unwrap()),
+                        expected,
+                        "'{raw_range}' matching '{version}'"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_ranges() {
+        check_ranges(&[
+            ("^0.0.x", &["0.0.0", "0.0.9"], &["0.1.0", "1.0.0"]),
+            ("^0.0.3", &["0.0.3"], &["0.0.2", "0.0.4"]),
+            ("^0.2.3", &["0.2.3", "0.2.9"], &["0.2.2", "0.3.0"]),
+            ("^1.2.3", &["1.2.3", "1.9.0"], &["1.2.2", "2.0.0"]),
+            ("~1", &["1.0.0", "1.9.9"], &["0.9.9", "2.0.0"]),
+            ("~1.2.3", &["1.2.3", "1.2.9"], &["1.2.2", "1.3.0"]),
+            ("~>1.2", &["1.2.0", "1.2.9"], &["1.1.9", "1.3.0"]),
+            ("1.2 - 2", &["1.2.0", "2.0.0", "2.9.9"], &["1.1.9", "3.0.0"]),
+            ("1.2.3 - 2.3.4", &["1.2.3", "2.3.4"], &["1.2.2", "2.3.5"]),
+            (
+                ">=1.2.3 <2 || 3.x",
+                &["1.2.3", "1.9.0", "3.0.0", "3.4.5"],
+                &["1.2.2", "2.0.0", "2.9.9", "4.0.0"],
+            ),
+            ("*", &["0.0.0", "1.2.3", "99.0.0"], &[]),
+            ("", &["0.0.0", "1.2.3"], &[]),
+            ("x", &["1.2.3"], &[]),
+            (">= 1.0.0", &["1.0.0", "2.0.0"], &["0.9.9"]),
+            ("< 2.2.3 > 1.1.2", &["1.1.3", "2.2.2"], &["1.1.2", "2.2.3"]),
+            ("1.x", &["1.0.0", "1.9.9"], &["0.9.9", "2.0.0"]),
+            ("1.2.*", &["1.2.0", "1.2.9"], &["1.3.0"]),
+            (">1.2", &["1.3.0"], &["1.2.9"]),
+            ("<1.2", &["1.1.9"], &["1.2.0"]),
+            ("<=1.2", &["1.2.9"], &["1.3.0"]),
+            ("=v1.2.3", &["1.2.3"], &["1.2.4"]),
+            ("1.2.3+build", &["1.2.3"], &["1.2.4"]),
+        ]);
+    }
+
+    #[test]
+    fn matches_prereleases() {
+        check_ranges(&[
+            ("<2.0.0", &["1.9.9"], &["2.0.0-rc.1", "2.0.0"]),
+            (
+                ">=1.0.0-beta <1.0.0",
+                &["1.0.0-beta", "1.0.0-beta.2"],
+                &["1.0.0", "0.9.0"],
+            ),
+            ("^1.0.0", &["1.5.0"], &["1.5.0-beta", "2.0.0-0"]),
+            (
+                "^1.5.0-beta",
+                &["1.5.0-beta", "1.5.0-rc", "1.5.0", "1.6.0"],
+                &["1.6.0-beta"],
+            ),
+            (
+                "~1.2.3-alpha.1",
+                &["1.2.3-alpha.2", "1.2.9"],
+                &["1.2.3-alpha.0"],
+            ),
+            (
+                "1.0.0-beta.1",
+                &["1.0.0-beta.1"],
+                &["1.0.0-beta.2", "1.0.0"],
+            ),
+            ("*", &[], &["1.0.0-beta"]),
+        ]);
+    }
+
+    fn package_data(versions: &[&str], latest: Option<&str>) -> PackageData {
+        PackageData {
+            versions: versions
+                .iter()
+                .map(|version| {
+                    let version_data = VersionData::from_package_json(
+                        Default::default(),
+                        String::from("package"),
+                        version.to_string(),
+                        String::new(),
+                    );
+                    (version.to_string(), version_data)
+                })
+                .collect(),
+            dist_tags: latest
+                .map(|latest| HashMap::from([(LATEST.to_string(), latest.to_string())]))
+                .unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn sorts_versions_by_precedence() {
+        let package_data = package_data(
+            &[
+                "1.10.0",
+                "1.9.0",
+                "1.0.0",
+                "1.0.0-beta",
+                "not-a-version",
+                "1.0",
+            ],
+            None,
+        );
+
+        let sorted = Versions::sort(&package_data.versions)
+            .into_iter()
+            .map(|(_, version_str)| version_str.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(sorted, ["1.0.0-beta", "1.0.0", "1.9.0", "1.10.0"]);
+    }
+
+    #[test]
+    fn resolves_versions_with_prereleases() {
+        let package_data = package_data(
+            &[
+                "1.0.0-beta.2",
+                "1.0.0",
+                "1.9.0",
+                "2.0.0-rc.1",
+                "2.0.0",
+                "invalid",
+            ],
+            Some("2.0.0"),
+        );
+
+        let cases = [
+            ("<2.0.0", "1.9.0"),
+            (">=1.0.0-beta <1.0.0", "1.0.0-beta.2"),
+            ("^1.0.0", "1.9.0"),
+            ("^2.0.0-rc", "2.0.0"),
+            ("2.0.0-rc.1", "2.0.0-rc.1"),
+        ];
+
+        for (raw_range, expected) in cases {
+            let version_spec = Versions::parse_dependency_version(raw_range).unwrap();
+            let version = Versions::resolve_partial_version(&version_spec, &package_data).unwrap();
+
+            assert_eq!(version, expected, "{raw_range}");
+        }
+
+        // Anything the registry can't resolve by itself needs the full list of versions
+        for raw_range in ["<2.0.0", "^1.0.0-beta", ">=1.0.0 <2.0.0"] {
+            let version_spec = Versions::parse_dependency_version(raw_range).unwrap();
+            assert_eq!(
+                Versions::resolve_full_version(&version_spec),
+                None,
+                "{raw_range}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for raw_range in ["1.2.3.4", "a.b.c", "1.2-beta", ">=1.2.y", "~a"] {
+            assert!(
+                VersionRange::from_str(raw_range).is_err(),
+                "'{raw_range}' should not parse"
+            );
+        }
     }
 }